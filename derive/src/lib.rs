@@ -0,0 +1,286 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! `validate!` implements `sawtk::tp::Validate` for protobuf generated messages.
+//!
+//! Generated code can not be annotated, so the rules are read from a TOML file
+//! whose path is relative to the manifest directory of the calling crate:
+//!
+//! ```toml
+//! ["crate::messages::wallet::Transfer"]
+//! from = ["non_empty", "wallet"]
+//! signer = "public_key"
+//! id = "uuid"
+//! amount = { min = 1, max = 1000000 }
+//! inputs = { each = ["address"] }
+//! ```
+//!
+//! and applied with `sawtk_derive::validate!("rules/validate.toml");`.
+//!
+//! A field takes a rule name, an array of rules, or a table of rules. The rules
+//! are `non_empty`, `public_key` (`util::is_public_key`), `wallet`
+//! (`wallet::is_wallet`), `uuid` (`util::is_uuid`), `address`
+//! (`namespace::is_address`), `min` and `max` for numbers (use float literals
+//! for float fields), and `each` for applying rules to every item of a repeated
+//! field.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use std::env;
+use std::fs;
+use std::path::Path;
+use syn::{parse_macro_input, Ident, LitFloat, LitInt, LitStr};
+
+// ----------------------------------------------------------------------------
+
+enum Rule {
+    NonEmpty,
+    PublicKey,
+    Wallet,
+    Uuid,
+    Address,
+    Min(TokenStream2),
+    Max(TokenStream2),
+    Each(Vec<Rule>),
+}
+
+fn named(name: &str) -> Result<Rule, String> {
+    match name {
+        "non_empty" => Ok(Rule::NonEmpty),
+        "public_key" => Ok(Rule::PublicKey),
+        "wallet" => Ok(Rule::Wallet),
+        "uuid" => Ok(Rule::Uuid),
+        "address" => Ok(Rule::Address),
+        x => Err(format!("unknown rule {}", x)),
+    }
+}
+
+fn number(value: &toml::Value) -> Result<TokenStream2, String> {
+    match value {
+        toml::Value::Integer(x) => {
+            let lit = LitInt::new(&x.to_string(), Span::call_site());
+            Ok(quote!(#lit))
+        }
+        toml::Value::Float(x) => {
+            let lit = LitFloat::new(&format!("{:?}", x), Span::call_site());
+            Ok(quote!(#lit))
+        }
+        x => Err(format!("{} is not a number", x)),
+    }
+}
+
+fn parse_table(table: &toml::Table) -> Result<Vec<Rule>, String> {
+    let mut rules = Vec::new();
+
+    for (key, value) in table {
+        match key.as_str() {
+            "min" => rules.push(Rule::Min(number(value)?)),
+            "max" => rules.push(Rule::Max(number(value)?)),
+            "each" => rules.push(Rule::Each(parse_rules(value)?)),
+            name => match value {
+                toml::Value::Boolean(true) => rules.push(named(name)?),
+                toml::Value::Boolean(false) => {}
+                x => return Err(format!("rule {} takes a boolean, not {}", name, x)),
+            },
+        }
+    }
+
+    Ok(rules)
+}
+
+fn parse_rules(value: &toml::Value) -> Result<Vec<Rule>, String> {
+    match value {
+        toml::Value::String(name) => Ok(vec![named(name)?]),
+        toml::Value::Table(table) => parse_table(table),
+        toml::Value::Array(items) => {
+            let mut rules = Vec::new();
+            for item in items {
+                rules.extend(parse_rules(item)?);
+            }
+            Ok(rules)
+        }
+        x => Err(format!("invalid rule {}", x)),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+fn invalid(name: &str, reason: &str) -> TokenStream2 {
+    let msg = format!("{}: {}", name, reason);
+    quote! {
+        return Err(::sawtk::tp::Error::Invalid(#msg.to_string()));
+    }
+}
+
+fn check(rule: &Rule, value: &TokenStream2, name: &str) -> TokenStream2 {
+    match rule {
+        Rule::NonEmpty => {
+            let err = invalid(name, "empty");
+            quote! { if #value.is_empty() { #err } }
+        }
+        Rule::PublicKey => {
+            let err = invalid(name, "invalid public key");
+            quote! { if !::sawtk::util::is_public_key(&#value) { #err } }
+        }
+        Rule::Wallet => {
+            let err = invalid(name, "invalid wallet");
+            quote! { if !::sawtk::wallet::is_wallet(&#value) { #err } }
+        }
+        Rule::Uuid => {
+            let err = invalid(name, "invalid uuid");
+            quote! { if !::sawtk::util::is_uuid(&#value) { #err } }
+        }
+        Rule::Address => {
+            let err = invalid(name, "invalid address");
+            quote! { if !::sawtk::namespace::is_address(&#value) { #err } }
+        }
+        Rule::Min(lit) => {
+            let err = invalid(name, &format!("less than {}", lit));
+            quote! { if #value < #lit { #err } }
+        }
+        Rule::Max(lit) => {
+            let err = invalid(name, &format!("greater than {}", lit));
+            quote! { if #value > #lit { #err } }
+        }
+        Rule::Each(rules) => {
+            let item = quote!((*x));
+            let checks = rules.iter().map(|r| check(r, &item, name));
+            quote! {
+                for x in #value.iter() {
+                    #(#checks)*
+                }
+            }
+        }
+    }
+}
+
+fn expand(content: &str) -> Result<TokenStream2, String> {
+    let table: toml::Table = content.parse().map_err(|e| format!("{}", e))?;
+    let mut impls = Vec::new();
+
+    for (ty, fields) in table.iter() {
+        let path: syn::Path =
+            syn::parse_str(ty).map_err(|e| format!("invalid type {}: {}", ty, e))?;
+        let type_name = path
+            .segments
+            .last()
+            .map(|x| x.ident.to_string())
+            .unwrap_or_default();
+
+        let fields = match fields {
+            toml::Value::Table(x) => x,
+            x => return Err(format!("rules of {} must be a table, not {}", ty, x)),
+        };
+
+        let mut checks = Vec::new();
+        for (field, value) in fields.iter() {
+            let ident: Ident =
+                syn::parse_str(field).map_err(|e| format!("invalid field {}: {}", field, e))?;
+            let name = format!("{}.{}", type_name, field);
+            let rules = parse_rules(value).map_err(|e| format!("{}: {}", name, e))?;
+            let value = quote!(self.#ident);
+            checks.extend(rules.iter().map(|r| check(r, &value, &name)));
+        }
+
+        impls.push(quote! {
+            impl ::sawtk::tp::Validate for #path {
+                fn validate(&mut self) -> ::sawtk::tp::Result<()> {
+                    #(#checks)*
+                    Ok(())
+                }
+            }
+        });
+    }
+
+    Ok(quote!(#(#impls)*))
+}
+
+// ----------------------------------------------------------------------------
+
+/// Implements `sawtk::tp::Validate` with the rules in the given TOML file.
+#[proc_macro]
+pub fn validate(input: TokenStream) -> TokenStream {
+    let file = parse_macro_input!(input as LitStr);
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| String::from("."));
+    let path = Path::new(&root).join(file.value());
+    let display = path.display().to_string();
+
+    let result = fs::read_to_string(&path)
+        .map_err(|e| format!("couldn't read {}: {}", display, e))
+        .and_then(|content| expand(&content));
+
+    match result {
+        // includes the rules file for rebuilding when it changes.
+        Ok(impls) => quote! {
+            const _: &str = include_str!(#display);
+            #impls
+        }
+        .into(),
+        Err(e) => syn::Error::new(file.span(), e).to_compile_error().into(),
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static RULES: &str = r#"
+["crate::messages::wallet::Transfer"]
+from = ["non_empty", "wallet"]
+signer = "public_key"
+amount = { min = 1, max = 1000000 }
+inputs = { each = ["address"] }
+"#;
+
+    #[test]
+    fn test_expand() {
+        let code = expand(RULES).unwrap().to_string();
+        assert!(code.contains(
+            "impl :: sawtk :: tp :: Validate for crate :: messages :: wallet :: Transfer"
+        ));
+        assert!(code.contains("self . from . is_empty ()"));
+        assert!(code.contains(":: sawtk :: wallet :: is_wallet (& self . from)"));
+        assert!(code.contains(":: sawtk :: util :: is_public_key (& self . signer)"));
+        assert!(code.contains("self . amount < 1"));
+        assert!(code.contains("self . amount > 1000000"));
+        assert!(code.contains("for x in self . inputs . iter ()"));
+        assert!(code.contains(":: sawtk :: namespace :: is_address (& (* x))"));
+        assert!(code.contains("Transfer.from: invalid wallet"));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(expand("[Transfer]\nfrom = \"unknown\"").is_err());
+        assert!(expand("[Transfer]\namount = { min = \"a\" }").is_err());
+        assert!(expand("[Transfer]\nfrom = { wallet = 1 }").is_err());
+        assert!(expand("Transfer = 1").is_err());
+        assert!(expand("[\"not a type\"]\nfrom = \"wallet\"").is_err());
+    }
+}
@@ -0,0 +1,323 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Subscription of events emitted by `tp::add_event`.
+
+use super::{reply_ping, send_request};
+use crate::namespace::Namespace;
+use crate::{parse, Error, Result};
+use protobuf::{self, RepeatedField};
+use sawtooth_sdk::messages::client_event::{
+    ClientEventsSubscribeRequest, ClientEventsSubscribeResponse,
+    ClientEventsSubscribeResponse_Status, ClientEventsUnsubscribeRequest,
+    ClientEventsUnsubscribeResponse, ClientEventsUnsubscribeResponse_Status,
+};
+use sawtooth_sdk::messages::events::{
+    Event, EventFilter, EventFilter_FilterType, EventList, EventSubscription,
+};
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messaging::stream::{
+    MessageConnection, MessageReceiver, MessageSender, ReceiveError,
+};
+use sawtooth_sdk::messaging::zmq_stream::{ZmqMessageConnection, ZmqMessageSender};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+pub static STATE_DELTA: &str = "sawtooth/state-delta";
+pub static BLOCK_COMMIT: &str = "sawtooth/block-commit";
+
+static TIMEOUT: Duration = Duration::from_secs(30);
+
+// -----------------------------------------------------------------------------
+
+pub fn filter(key: &str, match_string: &str, filter_type: EventFilter_FilterType) -> EventFilter {
+    EventFilter {
+        key: key.to_string(),
+        match_string: match_string.to_string(),
+        filter_type,
+        ..EventFilter::default()
+    }
+}
+
+pub fn subscription(event_type: &str, filters: &[EventFilter]) -> EventSubscription {
+    EventSubscription {
+        event_type: event_type.to_string(),
+        filters: RepeatedField::from_slice(filters),
+        ..EventSubscription::default()
+    }
+}
+
+/// Subscribes the state changes of all addresses in namespace `ns`.
+pub fn state_delta(ns: &dyn Namespace) -> EventSubscription {
+    subscription(
+        STATE_DELTA,
+        &[filter(
+            "address",
+            &format!("^{}.*", ns.prefix()),
+            EventFilter_FilterType::REGEX_ANY,
+        )],
+    )
+}
+
+pub fn subscribe_request(
+    subscriptions: &[EventSubscription],
+    last_known_block_ids: &[String],
+) -> ClientEventsSubscribeRequest {
+    ClientEventsSubscribeRequest {
+        subscriptions: RepeatedField::from_slice(subscriptions),
+        last_known_block_ids: RepeatedField::from_slice(last_known_block_ids),
+        ..ClientEventsSubscribeRequest::default()
+    }
+}
+
+/// Decodes the data of `event`.
+pub fn decode<T: protobuf::Message>(event: &Event) -> Result<T> {
+    parse::<T>(&event.data)
+}
+
+/// Returns the value of the first attribute of `event` named `key`.
+pub fn attribute<'a>(event: &'a Event, key: &str) -> Option<&'a str> {
+    event
+        .attributes
+        .iter()
+        .find(|x| x.key == key)
+        .map(|x| x.value.as_str())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Event subscription over the component endpoint of a validator, e.g.
+/// `tcp://localhost:4004`. Pings of the validator are answered while
+/// receiving events.
+pub struct EventClient {
+    sender: ZmqMessageSender,
+    receiver: MessageReceiver,
+    closed: bool,
+}
+
+impl EventClient {
+    pub fn subscribe(
+        url: &str,
+        subscriptions: &[EventSubscription],
+        last_known_block_ids: &[String],
+    ) -> Result<Self> {
+        let (mut sender, receiver) = ZmqMessageConnection::new(url).create();
+
+        let request = subscribe_request(subscriptions, last_known_block_ids);
+        let result = send_request::<ClientEventsSubscribeResponse>(
+            &sender,
+            Message_MessageType::CLIENT_EVENTS_SUBSCRIBE_REQUEST,
+            &request,
+            TIMEOUT,
+        )
+        .and_then(|resp| match resp.status {
+            ClientEventsSubscribeResponse_Status::OK => Ok(()),
+            status => Err(Error::Response(format!(
+                "subscribe {:?}: {}",
+                status, resp.response_message
+            ))),
+        });
+
+        match result {
+            Ok(_) => Ok(EventClient {
+                sender,
+                receiver,
+                closed: false,
+            }),
+            Err(e) => {
+                sender.close();
+                Err(e)
+            }
+        }
+    }
+
+    /// Blocks until the next list of events arrives.
+    pub fn recv(&self) -> Result<Vec<Event>> {
+        loop {
+            match self.receiver.recv() {
+                Ok(Ok(msg)) => {
+                    if msg.get_message_type() == Message_MessageType::CLIENT_EVENTS {
+                        return parse::<EventList>(msg.get_content()).map(|x| x.events.into_vec());
+                    }
+                    reply_ping(&self.sender, &msg);
+                }
+                Ok(Err(e)) => return Err(Error::Receive(e)),
+                Err(e) => return Err(Error::Receive(ReceiveError::ChannelError(e))),
+            }
+        }
+    }
+
+    /// Like `recv`, but fails with `Error::Timeout` after `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Vec<Event>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(left) {
+                Ok(Ok(msg)) => {
+                    if msg.get_message_type() == Message_MessageType::CLIENT_EVENTS {
+                        return parse::<EventList>(msg.get_content()).map(|x| x.events.into_vec());
+                    }
+                    reply_ping(&self.sender, &msg);
+                }
+                Ok(Err(e)) => return Err(Error::Receive(e)),
+                Err(RecvTimeoutError::Timeout) => return Err(Error::Timeout(Vec::new())),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::Receive(ReceiveError::DisconnectedError))
+                }
+            }
+        }
+    }
+
+    pub fn unsubscribe(mut self) -> Result<()> {
+        let resp = send_request::<ClientEventsUnsubscribeResponse>(
+            &self.sender,
+            Message_MessageType::CLIENT_EVENTS_UNSUBSCRIBE_REQUEST,
+            &ClientEventsUnsubscribeRequest::default(),
+            TIMEOUT,
+        );
+        self.sender.close();
+        self.closed = true;
+
+        match resp?.status {
+            ClientEventsUnsubscribeResponse_Status::OK => Ok(()),
+            status => Err(Error::Response(format!("unsubscribe {:?}", status))),
+        }
+    }
+}
+
+impl Drop for EventClient {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.sender.close();
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::responder;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use protobuf::Message;
+    use sawtooth_sdk::messages::client_event::ClientEventsUnsubscribeResponse;
+    use sawtooth_sdk::messages::events::Event_Attribute;
+    use sawtooth_sdk::messages::network::PingRequest;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_state_delta() {
+        let sub = state_delta(namespace::new("intkey").as_ref());
+        assert_eq!(STATE_DELTA, sub.event_type);
+        assert_eq!("address", sub.filters[0].key);
+        assert_eq!("^1cf126.*", sub.filters[0].match_string);
+    }
+
+    #[test]
+    fn test_event_client() {
+        let ponged = Arc::new(AtomicBool::new(false));
+        let inner = ponged.clone();
+
+        let url = responder::serve(move |msg| match msg.get_message_type() {
+            Message_MessageType::CLIENT_EVENTS_SUBSCRIBE_REQUEST => {
+                let req = parse::<ClientEventsSubscribeRequest>(msg.get_content()).unwrap();
+                assert_eq!("intkey/set", req.subscriptions[0].event_type);
+
+                let resp = ClientEventsSubscribeResponse {
+                    status: ClientEventsSubscribeResponse_Status::OK,
+                    ..ClientEventsSubscribeResponse::default()
+                };
+
+                let data = TPRequest {
+                    cmd: 5,
+                    ..TPRequest::default()
+                };
+                let event = Event {
+                    event_type: "intkey/set".to_string(),
+                    attributes: RepeatedField::from_vec(vec![Event_Attribute {
+                        key: "key".to_string(),
+                        value: "a1".to_string(),
+                        ..Event_Attribute::default()
+                    }]),
+                    data: data.write_to_bytes().unwrap(),
+                    ..Event::default()
+                };
+                let list = EventList {
+                    events: RepeatedField::from_vec(vec![event]),
+                    ..EventList::default()
+                };
+
+                vec![
+                    responder::message(
+                        Message_MessageType::CLIENT_EVENTS_SUBSCRIBE_RESPONSE,
+                        msg.get_correlation_id(),
+                        &resp,
+                    ),
+                    responder::message(
+                        Message_MessageType::PING_REQUEST,
+                        "ping",
+                        &PingRequest::default(),
+                    ),
+                    responder::message(Message_MessageType::CLIENT_EVENTS, "events", &list),
+                ]
+            }
+            Message_MessageType::PING_RESPONSE => {
+                assert_eq!("ping", msg.get_correlation_id());
+                inner.store(true, Ordering::SeqCst);
+                Vec::new()
+            }
+            Message_MessageType::CLIENT_EVENTS_UNSUBSCRIBE_REQUEST => {
+                let resp = ClientEventsUnsubscribeResponse {
+                    status: ClientEventsUnsubscribeResponse_Status::OK,
+                    ..ClientEventsUnsubscribeResponse::default()
+                };
+                vec![responder::message(
+                    Message_MessageType::CLIENT_EVENTS_UNSUBSCRIBE_RESPONSE,
+                    msg.get_correlation_id(),
+                    &resp,
+                )]
+            }
+            _ => Vec::new(),
+        });
+
+        let client = EventClient::subscribe(&url, &[subscription("intkey/set", &[])], &[]).unwrap();
+        let events = client.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(1, events.len());
+        assert_eq!(Some("a1"), attribute(&events[0], "key"));
+        assert_eq!(None, attribute(&events[0], "none"));
+        assert_eq!(5, decode::<TPRequest>(&events[0]).unwrap().cmd);
+
+        match client.recv_timeout(Duration::from_millis(100)) {
+            Err(Error::Timeout(ids)) => assert!(ids.is_empty()),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        client.unsubscribe().unwrap();
+        assert!(ponged.load(Ordering::SeqCst));
+    }
+}
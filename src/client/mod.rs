@@ -0,0 +1,223 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Clients of the sawtooth validator.
+
+use super::{parse, Error, Result};
+use crate::util;
+use protobuf::{self, Message};
+use sawtooth_sdk::messages::network::PingResponse;
+use sawtooth_sdk::messages::validator::{Message as ValidatorMessage, Message_MessageType};
+use sawtooth_sdk::messaging::stream::MessageSender;
+use sawtooth_sdk::messaging::zmq_stream::ZmqMessageSender;
+use std::fmt;
+use std::time::Duration;
+
+pub mod events;
+#[cfg(feature = "rest")]
+pub mod rest;
+pub mod validator;
+
+// -----------------------------------------------------------------------------
+
+/// One page of a listing. `head` is the block or state root the page is read
+/// from, and `next` is the start of the following page.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub head: String,
+    pub next: Option<String>,
+}
+
+/// Reads all pages from `page`, called with the head and the start of each
+/// page. The head of the first page is passed on to the following ones, so
+/// that changes committed meanwhile do not mix into the result.
+pub(crate) fn read_pages<T, F>(mut page: F) -> Result<Vec<T>>
+where
+    F: FnMut(Option<&str>, Option<&str>) -> Result<Page<T>>,
+{
+    let mut ret: Vec<T> = Vec::new();
+    let mut head: Option<String> = None;
+    let mut start: Option<String> = None;
+    loop {
+        let page = page(head.as_deref(), start.as_deref())?;
+        if head.is_none() && !page.head.is_empty() {
+            head = Some(page.head);
+        }
+        ret.extend(page.data);
+        match page.next {
+            Some(next) => start = Some(next),
+            None => return Ok(ret),
+        }
+    }
+}
+
+/// Error replied by the REST API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: i64,
+    pub title: String,
+    pub message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}): {}",
+            self.status, self.title, self.code, self.message
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Sends `request` with a new correlation id and decodes the reply.
+pub(crate) fn send_request<T: protobuf::Message>(
+    sender: &ZmqMessageSender,
+    msg_type: Message_MessageType,
+    request: &dyn Message,
+    timeout: Duration,
+) -> Result<T> {
+    let bytes = request.write_to_bytes().map_err(Error::Protobuf)?;
+    let mut future = sender
+        .send(msg_type, &util::uuid(), &bytes)
+        .map_err(Error::Send)?;
+    let reply = future.get_timeout(timeout).map_err(Error::Receive)?;
+    parse::<T>(reply.get_content())
+}
+
+/// Answers `msg` with a `PING_RESPONSE` if it is a ping of the validator, which
+/// drops connections not answering. Returns whether `msg` is a ping.
+pub(crate) fn reply_ping(sender: &ZmqMessageSender, msg: &ValidatorMessage) -> bool {
+    if msg.get_message_type() != Message_MessageType::PING_REQUEST {
+        return false;
+    }
+
+    let bytes = PingResponse::default().write_to_bytes().unwrap_or_default();
+    let _ = sender.reply(
+        Message_MessageType::PING_RESPONSE,
+        msg.get_correlation_id(),
+        &bytes,
+    );
+    true
+}
+
+// -----------------------------------------------------------------------------
+
+/// Stand-in of the component endpoint of a validator for tests.
+#[cfg(test)]
+pub(crate) mod responder {
+    use protobuf::Message;
+    use sawtooth_sdk::messages::validator::{Message as ValidatorMessage, Message_MessageType};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+
+    pub fn message(
+        msg_type: Message_MessageType,
+        correlation_id: &str,
+        content: &dyn Message,
+    ) -> ValidatorMessage {
+        let mut msg = ValidatorMessage::new();
+        msg.set_message_type(msg_type);
+        msg.set_correlation_id(correlation_id.to_string());
+        msg.set_content(content.write_to_bytes().unwrap());
+        msg
+    }
+
+    /// Binds a random local port and replies every received message with the
+    /// messages returned by `handler`. Returns the url to connect to. The
+    /// responder runs until the tests exit.
+    pub fn serve<F>(handler: F) -> String
+    where
+        F: FnMut(ValidatorMessage) -> Vec<ValidatorMessage> + Send + 'static,
+    {
+        let responder = bind("tcp://127.0.0.1:*", handler);
+        let url = responder.url.clone();
+        std::mem::forget(responder);
+        url
+    }
+
+    /// Responder bound by `bind`, closed when dropped.
+    pub struct Responder {
+        pub url: String,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Drop for Responder {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    /// Like `serve`, but binds `endpoint`, e.g. the url of a dropped
+    /// responder to restart it.
+    pub fn bind<F>(endpoint: &str, mut handler: F) -> Responder
+    where
+        F: FnMut(ValidatorMessage) -> Vec<ValidatorMessage> + Send + 'static,
+    {
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::ROUTER).unwrap();
+        socket.set_linger(0).unwrap();
+        socket.set_rcvtimeo(50).unwrap();
+        socket.bind(endpoint).unwrap();
+        let url = socket.get_last_endpoint().unwrap().unwrap();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = thread::spawn(move || {
+            let _ctx = ctx;
+            while !stopped.load(Ordering::SeqCst) {
+                let mut parts = match socket.recv_multipart(0) {
+                    Ok(parts) => parts,
+                    Err(zmq::Error::EAGAIN) => continue,
+                    Err(e) => panic!("responder {}", e),
+                };
+                let bytes = parts.pop().unwrap();
+                let identity = parts.remove(0);
+                let request = super::parse::<ValidatorMessage>(&bytes).unwrap();
+
+                for reply in handler(request) {
+                    let bytes = reply.write_to_bytes().unwrap();
+                    socket
+                        .send_multipart(vec![identity.clone(), bytes], 0)
+                        .unwrap();
+                }
+            }
+        });
+
+        Responder {
+            url,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
@@ -0,0 +1,782 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Blocking client of the sawtooth REST API, e.g. `http://localhost:8008`.
+
+use super::{read_pages, ApiError, Page};
+use crate::namespace::Namespace;
+use crate::{parse, Error, Result};
+use protobuf::{self, Message, RepeatedField};
+use sawtooth_sdk::messages::batch::BatchList;
+use sawtooth_sdk::messages::events::{Event, Event_Attribute};
+use sawtooth_sdk::messages::transaction_receipt::{
+    StateChange, StateChange_Type, TransactionReceipt,
+};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::Deserialize;
+use std::thread;
+use std::time::{Duration, Instant};
+use ureq::{Agent, AgentBuilder, Request, Response};
+
+static TIMEOUT: Duration = Duration::from_secs(30);
+static POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Default page size of `list_state`, the maximum allowed by the REST API.
+pub static PAGE_LIMIT: usize = 1000;
+
+/// Maximum number of ids put into the query string. More ids are posted as a
+/// JSON array, or the url would exceed the limits of servers and proxies.
+pub static QUERY_ID_LIMIT: usize = 15;
+
+// -----------------------------------------------------------------------------
+
+fn base64<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<u8>, D::Error> {
+    let s = String::deserialize(d)?;
+    base64::decode(&s).map_err(de::Error::custom)
+}
+
+fn base64_list<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Vec<Vec<u8>>, D::Error> {
+    let list = Vec::<String>::deserialize(d)?;
+    list.iter()
+        .map(|s| base64::decode(s).map_err(de::Error::custom))
+        .collect()
+}
+
+/// uint64 fields are rendered as strings by the REST API.
+fn number<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Str(String),
+        Num(u64),
+    }
+
+    match Number::deserialize(d)? {
+        Number::Str(s) => s.parse().map_err(de::Error::custom),
+        Number::Num(n) => Ok(n),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Status {
+    Committed,
+    Invalid,
+    Pending,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvalidTransaction {
+    pub id: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, deserialize_with = "base64")]
+    pub extended_data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchStatus {
+    pub id: String,
+    pub status: Status,
+    #[serde(default)]
+    pub invalid_transactions: Vec<InvalidTransaction>,
+}
+
+impl Status {
+    /// Whether the batch is committed or invalid, and will not change.
+    pub fn is_final(self) -> bool {
+        self == Status::Committed || self == Status::Invalid
+    }
+}
+
+impl BatchStatus {
+    pub fn is_final(&self) -> bool {
+        self.status.is_final()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct State {
+    pub address: String,
+    #[serde(deserialize_with = "base64")]
+    pub data: Vec<u8>,
+}
+
+impl State {
+    pub fn decode<T: protobuf::Message>(&self) -> Result<T> {
+        parse::<T>(&self.data)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransactionHeader {
+    pub batcher_public_key: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    pub family_name: String,
+    pub family_version: String,
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub nonce: String,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    pub payload_sha512: String,
+    pub signer_public_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transaction {
+    pub header: TransactionHeader,
+    pub header_signature: String,
+    #[serde(deserialize_with = "base64")]
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchHeader {
+    pub signer_public_key: String,
+    #[serde(default)]
+    pub transaction_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Batch {
+    pub header: BatchHeader,
+    pub header_signature: String,
+    #[serde(default)]
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockHeader {
+    #[serde(deserialize_with = "number")]
+    pub block_num: u64,
+    pub previous_block_id: String,
+    pub signer_public_key: String,
+    #[serde(default)]
+    pub batch_ids: Vec<String>,
+    #[serde(default, deserialize_with = "base64")]
+    pub consensus: Vec<u8>,
+    pub state_root_hash: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub header_signature: String,
+    #[serde(default)]
+    pub batches: Vec<Batch>,
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    data: T,
+    #[serde(default)]
+    head: String,
+    #[serde(default)]
+    paging: Option<Paging>,
+}
+
+#[derive(Deserialize)]
+struct Paging {
+    #[serde(default)]
+    next_position: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ReceiptJson {
+    transaction_id: String,
+    #[serde(default)]
+    state_changes: Vec<StateChangeJson>,
+    #[serde(default)]
+    events: Vec<EventJson>,
+    #[serde(default, deserialize_with = "base64_list")]
+    data: Vec<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+struct StateChangeJson {
+    address: String,
+    #[serde(default, deserialize_with = "base64")]
+    value: Vec<u8>,
+    #[serde(rename = "type")]
+    change_type: String,
+}
+
+#[derive(Deserialize)]
+struct EventJson {
+    event_type: String,
+    #[serde(default)]
+    attributes: Vec<AttributeJson>,
+    #[serde(default, deserialize_with = "base64")]
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct AttributeJson {
+    key: String,
+    value: String,
+}
+
+impl From<ReceiptJson> for TransactionReceipt {
+    fn from(receipt: ReceiptJson) -> Self {
+        let state_changes = receipt
+            .state_changes
+            .into_iter()
+            .map(|x| StateChange {
+                address: x.address,
+                value: x.value,
+                field_type: match x.change_type.as_str() {
+                    "SET" => StateChange_Type::SET,
+                    "DELETE" => StateChange_Type::DELETE,
+                    _ => StateChange_Type::TYPE_UNSET,
+                },
+                ..StateChange::default()
+            })
+            .collect();
+
+        let events = receipt
+            .events
+            .into_iter()
+            .map(|x| Event {
+                event_type: x.event_type,
+                attributes: x
+                    .attributes
+                    .into_iter()
+                    .map(|a| Event_Attribute {
+                        key: a.key,
+                        value: a.value,
+                        ..Event_Attribute::default()
+                    })
+                    .collect(),
+                data: x.data,
+                ..Event::default()
+            })
+            .collect();
+
+        TransactionReceipt {
+            state_changes,
+            events,
+            data: RepeatedField::from_vec(receipt.data),
+            transaction_id: receipt.transaction_id,
+            ..TransactionReceipt::default()
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+fn api_error(status: u16, resp: Response) -> Error {
+    let text = resp.into_string().unwrap_or_default();
+    let error = match serde_json::from_str::<ErrorBody>(&text) {
+        Ok(body) => ApiError {
+            status,
+            code: body.error.code,
+            title: body.error.title,
+            message: body.error.message,
+        },
+        Err(_) => ApiError {
+            status,
+            code: 0,
+            title: String::new(),
+            message: text,
+        },
+    };
+    Error::Api(error)
+}
+
+fn response(result: std::result::Result<Response, ureq::Error>) -> Result<Response> {
+    match result {
+        Ok(resp) => Ok(resp),
+        Err(ureq::Error::Status(status, resp)) => Err(api_error(status, resp)),
+        Err(e) => Err(Error::Http(e.to_string())),
+    }
+}
+
+fn envelope<T: DeserializeOwned>(resp: Response) -> Result<Envelope<T>> {
+    resp.into_json::<Envelope<T>>()
+        .map_err(|e| Error::Http(e.to_string()))
+}
+
+fn is_not_found(e: &Error) -> bool {
+    match e {
+        Error::Api(x) => x.status == 404,
+        _ => false,
+    }
+}
+
+/// Client of the REST API.
+pub struct RestClient {
+    url: String,
+    timeout: Duration,
+    agent: Agent,
+}
+
+impl RestClient {
+    pub fn new(url: &str) -> Self {
+        RestClient::with_timeout(url, TIMEOUT)
+    }
+
+    /// Creates a client whose requests fail after `timeout`.
+    pub fn with_timeout(url: &str, timeout: Duration) -> Self {
+        RestClient {
+            url: url.trim_end_matches('/').to_string(),
+            timeout,
+            agent: AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn call(&self, req: Request, body: Option<&[u8]>) -> Result<Response> {
+        response(match body {
+            Some(bytes) => req
+                .set("Content-Type", "application/octet-stream")
+                .send_bytes(bytes),
+            None => req.call(),
+        })
+    }
+
+    fn get<T: DeserializeOwned>(&self, req: Request) -> Result<Envelope<T>> {
+        envelope(self.call(req, None)?)
+    }
+
+    fn request(&self, method: &str, path: &str) -> Request {
+        self.agent.request(method, &format!("{}{}", self.url, path))
+    }
+
+    /// Requests `path` with `ids` in the query string, or posts them when
+    /// there are more than `QUERY_ID_LIMIT`. Further query parameters are
+    /// added by `build`.
+    fn by_ids<T, F>(&self, path: &str, ids: &[String], build: F) -> Result<Envelope<T>>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(Request) -> Request,
+    {
+        if ids.len() > QUERY_ID_LIMIT {
+            envelope(response(build(self.request("POST", path)).send_json(ids))?)
+        } else {
+            self.get::<T>(build(self.request("GET", path).query("id", &ids.join(","))))
+        }
+    }
+
+    // -------------------------------------------------------------------------
+
+    /// Posts `list` to `/batches` and returns the ids of the batches in it.
+    pub fn submit(&self, list: &BatchList) -> Result<Vec<String>> {
+        let bytes = list.write_to_bytes().map_err(Error::Protobuf)?;
+        self.call(self.request("POST", "/batches"), Some(&bytes))?;
+
+        Ok(list
+            .batches
+            .iter()
+            .map(|x| x.header_signature.clone())
+            .collect())
+    }
+
+    /// Returns the statuses of `ids`. A non-zero `wait` lets the REST API hold
+    /// the reply until the batches are committed or `wait` elapses.
+    pub fn batch_statuses(&self, ids: &[String], wait: Duration) -> Result<Vec<BatchStatus>> {
+        let timeout = self.timeout + wait;
+        let resp = self.by_ids::<Vec<BatchStatus>, _>("/batch_statuses", ids, |req| {
+            let req = req.timeout(timeout);
+            if wait.as_secs() > 0 {
+                req.query("wait", &wait.as_secs().to_string())
+            } else {
+                req
+            }
+        })?;
+        Ok(resp.data)
+    }
+
+    /// Polls the statuses of `ids` until all are final. Fails with
+    /// `Error::Timeout` listing the batches not final yet when `timeout`
+    /// elapses first.
+    pub fn wait(&self, ids: &[String], timeout: Duration) -> Result<Vec<BatchStatus>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let statuses = self.batch_statuses(ids, Duration::from_secs(left.as_secs()))?;
+
+            if statuses.iter().all(BatchStatus::is_final) {
+                return Ok(statuses);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout(
+                    statuses
+                        .into_iter()
+                        .filter(|x| !x.is_final())
+                        .map(|x| x.id)
+                        .collect(),
+                ));
+            }
+
+            thread::sleep(POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now())));
+        }
+    }
+
+    /// Submits `list` and waits for its batches as `wait` does.
+    pub fn submit_and_wait(&self, list: &BatchList, timeout: Duration) -> Result<Vec<BatchStatus>> {
+        let ids = self.submit(list)?;
+        self.wait(&ids, timeout)
+    }
+
+    // -------------------------------------------------------------------------
+
+    /// Returns the data at `address`, or `None` if not set.
+    pub fn state(&self, address: &str) -> Result<Option<Vec<u8>>> {
+        #[derive(Deserialize)]
+        struct Data(#[serde(deserialize_with = "base64")] Vec<u8>);
+
+        match self.get::<Data>(self.request("GET", &format!("/state/{}", address))) {
+            Ok(x) => Ok(Some(x.data.0)),
+            Err(ref e) if is_not_found(e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn state_message<T: protobuf::Message>(&self, address: &str) -> Result<Option<T>> {
+        match self.state(address)? {
+            Some(bytes) => parse::<T>(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns one page of the entries under `prefix`, starting at `start`.
+    /// The page is read from the state of block `head`, or of the chain head
+    /// if `None`.
+    pub fn state_page(
+        &self,
+        prefix: &str,
+        head: Option<&str>,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<Page<State>> {
+        let mut req = self
+            .request("GET", "/state")
+            .query("address", prefix)
+            .query("limit", &limit.to_string());
+
+        if let Some(head) = head {
+            req = req.query("head", head);
+        }
+
+        if let Some(start) = start {
+            req = req.query("start", start);
+        }
+
+        let resp = self.get::<Vec<State>>(req)?;
+        Ok(Page {
+            data: resp.data,
+            head: resp.head,
+            next: resp.paging.and_then(|x| x.next_position),
+        })
+    }
+
+    /// Returns all entries under `prefix`, with every page read at the block
+    /// of the first one.
+    pub fn list_state(&self, prefix: &str) -> Result<Vec<State>> {
+        read_pages(|head, start| self.state_page(prefix, head, start, PAGE_LIMIT))
+    }
+
+    pub fn namespace_state(&self, ns: &dyn Namespace) -> Result<Vec<State>> {
+        self.list_state(ns.prefix())
+    }
+
+    // -------------------------------------------------------------------------
+
+    pub fn block(&self, id: &str) -> Result<Block> {
+        Ok(self
+            .get::<Block>(self.request("GET", &format!("/blocks/{}", id)))?
+            .data)
+    }
+
+    pub fn transaction(&self, id: &str) -> Result<Transaction> {
+        Ok(self
+            .get::<Transaction>(self.request("GET", &format!("/transactions/{}", id)))?
+            .data)
+    }
+
+    /// Returns the receipts of the transactions `ids`, e.g. for
+    /// `tx::receipt_data`.
+    pub fn receipts(&self, ids: &[String]) -> Result<Vec<TransactionReceipt>> {
+        Ok(self
+            .by_ids::<Vec<ReceiptJson>, _>("/receipts", ids, |req| req)?
+            .data
+            .into_iter()
+            .map(TransactionReceipt::from)
+            .collect())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::tx::receipt_data;
+    use sawtooth_sdk::messages::batch::Batch as BatchMessage;
+    use tiny_http::{Header, Response as HttpResponse, Server};
+
+    /// Serves requests with `handler`, which gets the method, the url and the
+    /// body, and returns the status and the json reply.
+    fn serve<F>(mut handler: F) -> String
+    where
+        F: FnMut(&str, &str, Vec<u8>) -> (u16, String) + Send + 'static,
+    {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+
+        thread::spawn(move || {
+            for mut req in server.incoming_requests() {
+                let mut body = Vec::new();
+                req.as_reader().read_to_end(&mut body).unwrap();
+                let (status, json) = handler(&req.method().to_string(), req.url(), body);
+                let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+                req.respond(
+                    HttpResponse::from_string(json)
+                        .with_status_code(status)
+                        .with_header(header),
+                )
+                .unwrap();
+            }
+        });
+
+        format!("http://127.0.0.1:{}/", port)
+    }
+
+    fn encode(cmd: i32) -> String {
+        let msg = TPRequest {
+            cmd,
+            ..TPRequest::default()
+        };
+        base64::encode(msg.write_to_bytes().unwrap())
+    }
+
+    #[test]
+    fn test_submit_and_wait() {
+        let mut polls = 0;
+        let url = serve(move |method, url, body| match (method, url) {
+            ("POST", "/batches") => {
+                let mut list = BatchList::new();
+                list.merge_from_bytes(&body).unwrap();
+                assert_eq!("b1", list.batches[0].header_signature);
+                (202, r#"{"link": "/batch_statuses?id=b1"}"#.to_string())
+            }
+            ("GET", x) if x.starts_with("/batch_statuses?id=b1") => {
+                polls += 1;
+                let status = if polls < 2 { "PENDING" } else { "INVALID" };
+                let json = format!(
+                    r#"{{"data": [{{"id": "b1", "status": "{}", "invalid_transactions": [{{"id": "t1", "message": "bad"}}]}}]}}"#,
+                    status
+                );
+                (200, json)
+            }
+            _ => (404, String::new()),
+        });
+
+        let list = BatchList {
+            batches: RepeatedField::from_vec(vec![BatchMessage {
+                header_signature: "b1".to_string(),
+                ..BatchMessage::default()
+            }]),
+            ..BatchList::default()
+        };
+
+        let client = RestClient::new(&url);
+        let statuses = client
+            .submit_and_wait(&list, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(1, statuses.len());
+        assert_eq!(Status::Invalid, statuses[0].status);
+        assert!(statuses[0].is_final());
+        assert_eq!("bad", statuses[0].invalid_transactions[0].message);
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let url = serve(|_, _, _| {
+            let json = r#"{"data": [{"id": "b1", "status": "COMMITTED"}, {"id": "b2", "status": "PENDING"}]}"#;
+            (200, json.to_string())
+        });
+
+        let client = RestClient::new(&url);
+        let ids = vec!["b1".to_string(), "b2".to_string()];
+        match client.wait(&ids, Duration::from_secs(0)) {
+            Err(Error::Timeout(pending)) => assert_eq!(vec!["b2".to_string()], pending),
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_batch_statuses_post() {
+        let url = serve(|method, url, body| match (method, url) {
+            ("POST", "/batch_statuses?wait=3") => {
+                let ids: Vec<String> = serde_json::from_slice(&body).unwrap();
+                let data = ids
+                    .iter()
+                    .map(|x| format!(r#"{{"id": "{}", "status": "COMMITTED"}}"#, x))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                (200, format!(r#"{{"data": [{}]}}"#, data))
+            }
+            _ => (404, String::new()),
+        });
+
+        let ids: Vec<String> = (0..=QUERY_ID_LIMIT).map(|x| format!("b{}", x)).collect();
+        let statuses = RestClient::new(&url)
+            .batch_statuses(&ids, Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(ids, statuses.into_iter().map(|x| x.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_state() {
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+        let prefix = ns.prefix().to_string();
+
+        let (p1, p2, s1) = (a1.clone(), a2.clone(), format!("/state/{}", a1));
+        let url = serve(move |_, url, _| {
+            if url == s1 {
+                (200, format!(r#"{{"data": "{}", "head": "h1"}}"#, encode(1)))
+            } else if url.starts_with("/state/") {
+                let json =
+                    r#"{"error": {"code": 75, "title": "State Not Found", "message": "none"}}"#;
+                (404, json.to_string())
+            } else if url.starts_with(&format!("/state?address={}", prefix))
+                && !url.contains("start=")
+                && !url.contains("head=")
+            {
+                let json = format!(
+                    r#"{{"data": [{{"address": "{}", "data": "{}"}}], "head": "h1", "paging": {{"limit": 1000, "next_position": "{}"}}}}"#,
+                    p1,
+                    encode(1),
+                    p2
+                );
+                (200, json)
+            } else if url.contains(&format!("start={}", p2)) && url.contains("head=h1") {
+                let json = format!(
+                    r#"{{"data": [{{"address": "{}", "data": "{}"}}], "head": "h1", "paging": {{"limit": 1000}}}}"#,
+                    p2,
+                    encode(2)
+                );
+                (200, json)
+            } else {
+                (400, String::new())
+            }
+        });
+
+        let client = RestClient::new(&url);
+        let msg = client.state_message::<TPRequest>(&a1).unwrap().unwrap();
+        assert_eq!(1, msg.cmd);
+        assert!(client.state(&a2).unwrap().is_none());
+
+        let states = client.namespace_state(ns.as_ref()).unwrap();
+        assert_eq!(
+            vec![a1, a2],
+            states.iter().map(|x| x.address.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(2, states[1].decode::<TPRequest>().unwrap().cmd);
+    }
+
+    #[test]
+    fn test_api_error() {
+        let url = serve(|_, _, _| {
+            let json =
+                r#"{"error": {"code": 70, "title": "Invalid Resource Id", "message": "bad id"}}"#;
+            (400, json.to_string())
+        });
+
+        match RestClient::new(&url).block("x") {
+            Err(Error::Api(e)) => {
+                assert_eq!(400, e.status);
+                assert_eq!(70, e.code);
+                assert_eq!("bad id", e.message);
+            }
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_block_and_receipts() {
+        let url = serve(|_, url, _| {
+            if url.starts_with("/blocks/") {
+                let json = r#"{"data": {"header_signature": "k1", "header": {"block_num": "3", "previous_block_id": "k0", "signer_public_key": "pk", "batch_ids": ["b1"], "consensus": "", "state_root_hash": "r"}, "batches": [{"header_signature": "b1", "header": {"signer_public_key": "pk", "transaction_ids": ["t1"]}, "transactions": [{"header_signature": "t1", "payload": "", "header": {"batcher_public_key": "pk", "family_name": "intkey", "family_version": "1.0", "payload_sha512": "s", "signer_public_key": "pk"}}]}]}}"#;
+                (200, json.to_string())
+            } else {
+                let json = format!(
+                    r#"{{"data": [{{"transaction_id": "t1", "state_changes": [{{"address": "a", "value": "", "type": "DELETE"}}], "events": [{{"event_type": "e", "attributes": [{{"key": "k", "value": "v"}}], "data": ""}}], "data": ["{}"]}}]}}"#,
+                    encode(7)
+                );
+                (200, json)
+            }
+        });
+
+        let client = RestClient::new(&url);
+        let block = client.block("k1").unwrap();
+        assert_eq!(3, block.header.block_num);
+        assert_eq!(
+            "intkey",
+            block.batches[0].transactions[0].header.family_name
+        );
+
+        let receipts = client.receipts(&["t1".to_string()]).unwrap();
+        assert_eq!("t1", receipts[0].transaction_id);
+        assert_eq!(
+            StateChange_Type::DELETE,
+            receipts[0].state_changes[0].field_type
+        );
+        assert_eq!("v", receipts[0].events[0].attributes[0].value);
+        assert_eq!(7, receipt_data::<TPRequest>(&receipts[0]).unwrap()[0].cmd);
+    }
+}
@@ -0,0 +1,562 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Client of the component endpoint of a validator, e.g.
+//! `tcp://localhost:4004`, bypassing the REST API.
+
+use super::{read_pages, reply_ping, send_request, Page};
+use crate::{parse, Error, Result};
+use protobuf::{self, Message, RepeatedField, SingularPtrField};
+use sawtooth_sdk::messages::batch::{Batch, BatchList};
+use sawtooth_sdk::messages::client_batch_submit::{
+    ClientBatchStatus, ClientBatchStatusRequest, ClientBatchStatusResponse,
+    ClientBatchStatusResponse_Status, ClientBatchSubmitRequest, ClientBatchSubmitResponse,
+    ClientBatchSubmitResponse_Status,
+};
+use sawtooth_sdk::messages::client_list_control::ClientPagingControls;
+use sawtooth_sdk::messages::client_state::{
+    ClientStateGetRequest, ClientStateGetResponse, ClientStateGetResponse_Status,
+    ClientStateListRequest, ClientStateListResponse, ClientStateListResponse_Entry,
+    ClientStateListResponse_Status,
+};
+use sawtooth_sdk::messages::validator::Message_MessageType;
+use sawtooth_sdk::messaging::stream::{MessageConnection, MessageSender, ReceiveError};
+use sawtooth_sdk::messaging::zmq_stream::{ZmqMessageConnection, ZmqMessageSender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+static TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default page size of `list_state`.
+pub static PAGE_LIMIT: i32 = 1000;
+
+// -----------------------------------------------------------------------------
+
+/// Connects to `url` and answers the pings of the validator. Other unsolicited
+/// messages are dropped, or they would fill up the inbound channel and stall
+/// the connection.
+fn connect(url: &str) -> ZmqMessageSender {
+    let (sender, receiver) = ZmqMessageConnection::new(url).create();
+    let pong = sender.clone();
+
+    thread::spawn(move || {
+        for msg in receiver.iter() {
+            match msg {
+                Ok(msg) => {
+                    reply_ping(&pong, &msg);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    sender
+}
+
+fn is_disconnected(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Send(_) | Error::Receive(ReceiveError::DisconnectedError)
+    )
+}
+
+fn is_timeout(e: &Error) -> bool {
+    matches!(e, Error::Receive(ReceiveError::TimeoutError))
+}
+
+/// Current connection of a `ValidatorClient`. `generation` counts the
+/// reconnections, so that requests failing on an old connection do not
+/// replace a newer one.
+struct Connection {
+    sender: ZmqMessageSender,
+    generation: u64,
+}
+
+/// Client of the validator. Requests failing because the connection is lost
+/// are retried once on a new connection.
+///
+/// A DEALER socket seldom notices a lost validator, which rather shows up as
+/// request timeouts. A timed out request fails as it is, without retrying,
+/// but the client connects again for the following requests.
+///
+/// Requests from several threads share the connection and run concurrently,
+/// matched to their replies by correlation id.
+pub struct ValidatorClient {
+    url: String,
+    timeout: Duration,
+    connection: Mutex<Connection>,
+}
+
+impl ValidatorClient {
+    pub fn connect(url: &str) -> Self {
+        ValidatorClient::with_timeout(url, TIMEOUT)
+    }
+
+    /// Like `connect`, with requests timing out after `timeout` instead of 30
+    /// seconds.
+    pub fn with_timeout(url: &str, timeout: Duration) -> Self {
+        ValidatorClient {
+            url: url.to_string(),
+            timeout,
+            connection: Mutex::new(Connection {
+                sender: connect(url),
+                generation: 0,
+            }),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Drops the current connection and connects again.
+    pub fn reconnect(&self) {
+        let generation = self.sender().1;
+        self.replace(generation);
+    }
+
+    fn sender(&self) -> (ZmqMessageSender, u64) {
+        let conn = self.connection.lock().unwrap();
+        (conn.sender.clone(), conn.generation)
+    }
+
+    /// Connects again unless the connection of `generation` is already
+    /// replaced, and returns the current connection.
+    fn replace(&self, generation: u64) -> (ZmqMessageSender, u64) {
+        let mut conn = self.connection.lock().unwrap();
+        if conn.generation == generation {
+            conn.sender.close();
+            conn.sender = connect(&self.url);
+            conn.generation += 1;
+        }
+        (conn.sender.clone(), conn.generation)
+    }
+
+    fn request<T: protobuf::Message>(
+        &self,
+        msg_type: Message_MessageType,
+        request: &dyn Message,
+        timeout: Duration,
+    ) -> Result<T> {
+        let (sender, mut generation) = self.sender();
+        let mut result = send_request::<T>(&sender, msg_type, request, timeout);
+        if matches!(result, Err(ref e) if is_disconnected(e)) {
+            let (sender, current) = self.replace(generation);
+            generation = current;
+            result = send_request::<T>(&sender, msg_type, request, timeout);
+        }
+
+        // A late reply of the timed out request would break the connection,
+        // so it is not used any more either.
+        if matches!(result, Err(ref e) if is_timeout(e)) {
+            self.replace(generation);
+        }
+        result
+    }
+
+    // -------------------------------------------------------------------------
+
+    /// Submits `batches` and returns their ids.
+    pub fn submit(&self, batches: &[Batch]) -> Result<Vec<String>> {
+        let request = ClientBatchSubmitRequest {
+            batches: RepeatedField::from_slice(batches),
+            ..ClientBatchSubmitRequest::default()
+        };
+
+        let resp = self.request::<ClientBatchSubmitResponse>(
+            Message_MessageType::CLIENT_BATCH_SUBMIT_REQUEST,
+            &request,
+            self.timeout,
+        )?;
+
+        match resp.status {
+            ClientBatchSubmitResponse_Status::OK => {
+                Ok(batches.iter().map(|x| x.header_signature.clone()).collect())
+            }
+            status => Err(Error::Response(format!("batch submit {:?}", status))),
+        }
+    }
+
+    /// Submits the output of `tx::Batcher::to_list`.
+    pub fn submit_list(&self, list: &BatchList) -> Result<Vec<String>> {
+        self.submit(&list.batches)
+    }
+
+    /// Returns the statuses of `ids`. With `wait`, the validator holds the
+    /// reply until the batches are committed or `wait` elapses.
+    pub fn batch_statuses(
+        &self,
+        ids: &[String],
+        wait: Option<Duration>,
+    ) -> Result<Vec<ClientBatchStatus>> {
+        let mut request = ClientBatchStatusRequest {
+            batch_ids: RepeatedField::from_slice(ids),
+            ..ClientBatchStatusRequest::default()
+        };
+
+        let mut timeout = self.timeout;
+        if let Some(wait) = wait {
+            request.wait = true;
+            request.timeout = wait.as_secs() as u32;
+            timeout += wait;
+        }
+
+        let resp = self.request::<ClientBatchStatusResponse>(
+            Message_MessageType::CLIENT_BATCH_STATUS_REQUEST,
+            &request,
+            timeout,
+        )?;
+
+        match resp.status {
+            ClientBatchStatusResponse_Status::OK => Ok(resp.batch_statuses.into_vec()),
+            status => Err(Error::Response(format!("batch status {:?}", status))),
+        }
+    }
+
+    pub fn submit_and_wait(
+        &self,
+        list: &BatchList,
+        wait: Duration,
+    ) -> Result<Vec<ClientBatchStatus>> {
+        let ids = self.submit_list(list)?;
+        self.batch_statuses(&ids, Some(wait))
+    }
+
+    // -------------------------------------------------------------------------
+
+    /// Returns the data at `address` in the current state, or `None` if not
+    /// set.
+    pub fn state(&self, address: &str) -> Result<Option<Vec<u8>>> {
+        let request = ClientStateGetRequest {
+            address: address.to_string(),
+            ..ClientStateGetRequest::default()
+        };
+
+        let resp = self.request::<ClientStateGetResponse>(
+            Message_MessageType::CLIENT_STATE_GET_REQUEST,
+            &request,
+            self.timeout,
+        )?;
+
+        match resp.status {
+            ClientStateGetResponse_Status::OK => Ok(Some(resp.value)),
+            ClientStateGetResponse_Status::NO_RESOURCE => Ok(None),
+            status => Err(Error::Response(format!("state get {:?}", status))),
+        }
+    }
+
+    pub fn state_message<T: protobuf::Message>(&self, address: &str) -> Result<Option<T>> {
+        match self.state(address)? {
+            Some(bytes) => parse::<T>(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Lists up to `limit` entries under `prefix` from address `start` on, at
+    /// the state root `head`, or at the chain head if `None`.
+    pub fn state_page(
+        &self,
+        prefix: &str,
+        head: Option<&str>,
+        start: Option<&str>,
+        limit: i32,
+    ) -> Result<Page<ClientStateListResponse_Entry>> {
+        let paging = ClientPagingControls {
+            start: start.unwrap_or_default().to_string(),
+            limit,
+            ..ClientPagingControls::default()
+        };
+
+        let request = ClientStateListRequest {
+            state_root: head.unwrap_or_default().to_string(),
+            address: prefix.to_string(),
+            paging: SingularPtrField::some(paging),
+            ..ClientStateListRequest::default()
+        };
+
+        let resp = self.request::<ClientStateListResponse>(
+            Message_MessageType::CLIENT_STATE_LIST_REQUEST,
+            &request,
+            self.timeout,
+        )?;
+
+        match resp.status {
+            ClientStateListResponse_Status::OK => {}
+            ClientStateListResponse_Status::NO_RESOURCE => {
+                return Ok(Page {
+                    data: Vec::new(),
+                    head: resp.state_root,
+                    next: None,
+                })
+            }
+            status => return Err(Error::Response(format!("state list {:?}", status))),
+        }
+
+        let next = resp.get_paging().get_next();
+        Ok(Page {
+            next: if next.is_empty() {
+                None
+            } else {
+                Some(next.to_string())
+            },
+            data: resp.entries.into_vec(),
+            head: resp.state_root,
+        })
+    }
+
+    /// Returns all entries under `prefix` at one state root.
+    pub fn list_state(&self, prefix: &str) -> Result<Vec<ClientStateListResponse_Entry>> {
+        read_pages(|head, start| self.state_page(prefix, head, start, PAGE_LIMIT))
+    }
+}
+
+impl Drop for ValidatorClient {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = self.connection.lock() {
+            conn.sender.close();
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::responder;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use sawtooth_sdk::messages::client_batch_submit::ClientBatchStatus_Status;
+    use sawtooth_sdk::messages::client_list_control::ClientPagingResponse;
+    use sawtooth_sdk::messages::validator::Message as ValidatorMessage;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    fn batch(id: &str) -> Batch {
+        Batch {
+            header_signature: id.to_string(),
+            ..Batch::default()
+        }
+    }
+
+    #[test]
+    fn test_submit() {
+        let url = responder::serve(|msg| match msg.get_message_type() {
+            Message_MessageType::CLIENT_BATCH_SUBMIT_REQUEST => {
+                let req = parse::<ClientBatchSubmitRequest>(msg.get_content()).unwrap();
+                let status = if req.batches.len() > 1 {
+                    ClientBatchSubmitResponse_Status::QUEUE_FULL
+                } else {
+                    ClientBatchSubmitResponse_Status::OK
+                };
+                let resp = ClientBatchSubmitResponse {
+                    status,
+                    ..ClientBatchSubmitResponse::default()
+                };
+                vec![responder::message(
+                    Message_MessageType::CLIENT_BATCH_SUBMIT_RESPONSE,
+                    msg.get_correlation_id(),
+                    &resp,
+                )]
+            }
+            Message_MessageType::CLIENT_BATCH_STATUS_REQUEST => {
+                let req = parse::<ClientBatchStatusRequest>(msg.get_content()).unwrap();
+                assert!(req.wait);
+                assert_eq!(5, req.timeout);
+                let status = ClientBatchStatus {
+                    batch_id: req.batch_ids[0].clone(),
+                    status: ClientBatchStatus_Status::COMMITTED,
+                    ..ClientBatchStatus::default()
+                };
+                let resp = ClientBatchStatusResponse {
+                    status: ClientBatchStatusResponse_Status::OK,
+                    batch_statuses: RepeatedField::from_vec(vec![status]),
+                    ..ClientBatchStatusResponse::default()
+                };
+                vec![responder::message(
+                    Message_MessageType::CLIENT_BATCH_STATUS_RESPONSE,
+                    msg.get_correlation_id(),
+                    &resp,
+                )]
+            }
+            _ => Vec::new(),
+        });
+
+        let client = ValidatorClient::connect(&url);
+        let list = BatchList {
+            batches: RepeatedField::from_vec(vec![batch("b1")]),
+            ..BatchList::default()
+        };
+        let statuses = client
+            .submit_and_wait(&list, Duration::from_secs(5))
+            .unwrap();
+        assert_eq!("b1", statuses[0].batch_id);
+        assert_eq!(ClientBatchStatus_Status::COMMITTED, statuses[0].status);
+
+        match client.submit(&[batch("b1"), batch("b2")]) {
+            Err(Error::Response(_)) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        client.reconnect();
+        assert_eq!(vec!["b3"], client.submit(&[batch("b3")]).unwrap());
+    }
+
+    #[test]
+    fn test_reconnect_after_timeout() {
+        fn handler(msg: ValidatorMessage) -> Vec<ValidatorMessage> {
+            let resp = ClientStateGetResponse {
+                status: ClientStateGetResponse_Status::NO_RESOURCE,
+                ..ClientStateGetResponse::default()
+            };
+            vec![responder::message(
+                Message_MessageType::CLIENT_STATE_GET_RESPONSE,
+                msg.get_correlation_id(),
+                &resp,
+            )]
+        }
+
+        let responder = responder::bind("tcp://127.0.0.1:*", handler);
+        let url = responder.url.clone();
+        let client = ValidatorClient::with_timeout(&url, Duration::from_secs(1));
+        assert!(client.state("a1").unwrap().is_none());
+
+        drop(responder);
+        match client.state("a1") {
+            Err(Error::Receive(ReceiveError::TimeoutError)) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let _responder = responder::bind(&url, handler);
+        assert!(client.state("a1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_concurrent_requests() {
+        let url = responder::serve(|msg| {
+            let req = parse::<ClientStateGetRequest>(msg.get_content()).unwrap();
+            if req.address == "slow" {
+                return Vec::new();
+            }
+            let resp = ClientStateGetResponse {
+                status: ClientStateGetResponse_Status::NO_RESOURCE,
+                ..ClientStateGetResponse::default()
+            };
+            vec![responder::message(
+                Message_MessageType::CLIENT_STATE_GET_RESPONSE,
+                msg.get_correlation_id(),
+                &resp,
+            )]
+        });
+
+        let client = Arc::new(ValidatorClient::with_timeout(&url, Duration::from_secs(3)));
+        let slow = {
+            let client = client.clone();
+            thread::spawn(move || is_timeout(&client.state("slow").unwrap_err()))
+        };
+
+        thread::sleep(Duration::from_millis(200));
+        let start = Instant::now();
+        assert!(client.state("fast").unwrap().is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert!(slow.join().unwrap());
+        assert!(client.state("fast").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_state() {
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+
+        let (p1, p2) = (a1.clone(), a2.clone());
+        let url = responder::serve(move |msg| match msg.get_message_type() {
+            Message_MessageType::CLIENT_STATE_GET_REQUEST => {
+                let req = parse::<ClientStateGetRequest>(msg.get_content()).unwrap();
+                let mut resp = ClientStateGetResponse::default();
+                if req.address == p1 {
+                    resp.status = ClientStateGetResponse_Status::OK;
+                    let data = TPRequest {
+                        cmd: 1,
+                        ..TPRequest::default()
+                    };
+                    resp.value = data.write_to_bytes().unwrap();
+                } else {
+                    resp.status = ClientStateGetResponse_Status::NO_RESOURCE;
+                }
+                vec![responder::message(
+                    Message_MessageType::CLIENT_STATE_GET_RESPONSE,
+                    msg.get_correlation_id(),
+                    &resp,
+                )]
+            }
+            Message_MessageType::CLIENT_STATE_LIST_REQUEST => {
+                let req = parse::<ClientStateListRequest>(msg.get_content()).unwrap();
+                let first = req.get_paging().get_start().is_empty();
+                assert_eq!(first, req.state_root.is_empty());
+                if !first {
+                    assert_eq!("r1", req.state_root);
+                }
+                let entry = ClientStateListResponse_Entry {
+                    address: if first { p1.clone() } else { p2.clone() },
+                    ..ClientStateListResponse_Entry::default()
+                };
+                let paging = ClientPagingResponse {
+                    next: if first { p2.clone() } else { String::new() },
+                    ..ClientPagingResponse::default()
+                };
+                let resp = ClientStateListResponse {
+                    status: ClientStateListResponse_Status::OK,
+                    state_root: "r1".to_string(),
+                    entries: RepeatedField::from_vec(vec![entry]),
+                    paging: SingularPtrField::some(paging),
+                    ..ClientStateListResponse::default()
+                };
+                vec![responder::message(
+                    Message_MessageType::CLIENT_STATE_LIST_RESPONSE,
+                    msg.get_correlation_id(),
+                    &resp,
+                )]
+            }
+            _ => Vec::new(),
+        });
+
+        let client = ValidatorClient::connect(&url);
+        let msg = client.state_message::<TPRequest>(&a1).unwrap().unwrap();
+        assert_eq!(1, msg.cmd);
+        assert!(client.state(&a2).unwrap().is_none());
+
+        let entries = client.list_state(ns.prefix()).unwrap();
+        assert_eq!(
+            vec![a1, a2],
+            entries
+                .iter()
+                .map(|x| x.address.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -29,6 +29,7 @@ extern crate uuid;
 
 //use bs58::decode::Error as bs58dErr;
 use protobuf::ProtobufError;
+use sawtooth_sdk::messaging::stream::{ReceiveError, SendError};
 use sawtooth_sdk::signing::Error as SignErr;
 use std::{error, fmt, result};
 
@@ -37,12 +38,16 @@ pub mod util;
 pub mod wallet;
 
 // sawtooth toolkit
+pub mod client;
 pub mod messages;
 pub mod namespace;
 pub mod signing;
 pub mod tp;
 pub mod tx;
 
+#[cfg(test)]
+pub(crate) mod test_util;
+
 // ----------------------------------------------------------------------------
 
 #[derive(Debug)]
@@ -51,6 +56,23 @@ pub enum Error {
     OddLengthString(usize),
     Protobuf(ProtobufError),
     Signing(SignErr),
+    Send(SendError),
+    Receive(ReceiveError),
+    Response(String),
+    Http(String),
+    Api(client::ApiError),
+    InvalidAddress(String),
+    InvalidPublicKey(String),
+    InvalidTransactionId(String),
+    InvalidTemplate(String),
+    MissingField(&'static str),
+    TooLarge(String, usize),
+    Rejected(tx::Report),
+    Keystore(String),
+    Io(std::io::Error),
+    KeyFormat(String),
+    Derivation(String),
+    Timeout(Vec<String>),
     //InvalidLength(usize),
     //InvalidVersion(u8, u8),
     //BS58(bs58dErr),
@@ -64,6 +86,24 @@ impl fmt::Display for Error {
             Error::OddLengthString(len) => write!(f, "odd hex string length {}", len),
             Error::Protobuf(e) => write!(f, "encode/decode proto message {}", e),
             Error::Signing(e) => e.fmt(f),
+            Error::Send(e) => write!(f, "send message {}", e),
+            Error::Receive(e) => write!(f, "receive message {}", e),
+            Error::Response(msg) => write!(f, "response {}", msg),
+            Error::Http(msg) => write!(f, "http {}", msg),
+            Error::Api(e) => e.fmt(f),
+            Error::InvalidAddress(x) => write!(f, "invalid address {}", x),
+            Error::InvalidPublicKey(x) => write!(f, "invalid public key {}", x),
+            Error::InvalidTransactionId(x) => write!(f, "invalid transaction id {}", x),
+            Error::InvalidTemplate(x) => write!(f, "invalid template {}", x),
+            Error::MissingField(x) => write!(f, "{} not set", x),
+            Error::TooLarge(id, size) => write!(f, "{} too large, {} bytes", id, size),
+            Error::Rejected(report) => write!(f, "rejected {}", report),
+            Error::Keystore(msg) => write!(f, "keystore {}", msg),
+            Error::Io(e) => e.fmt(f),
+            Error::KeyFormat(msg) => write!(f, "key format {}", msg),
+            Error::Derivation(msg) => write!(f, "key derivation {}", msg),
+            Error::Timeout(ids) if ids.is_empty() => write!(f, "timeout"),
+            Error::Timeout(ids) => write!(f, "timeout waiting for {}", ids.join(", ")),
             /*Error::InvalidLength(len) => write!(f, "invalid length {}", len),
             Error::InvalidVersion(test, ans) => {
                 write!(f, "invalid version {}, must be {}", test, ans)
@@ -79,6 +119,9 @@ impl error::Error for Error {
         match &self {
             Error::Protobuf(ref e) => Some(e),
             Error::Signing(ref e) => Some(e),
+            Error::Send(ref e) => Some(e),
+            Error::Receive(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
             //Error::BS58(ref e) => Some(e),
             _ => None,
         }
@@ -90,6 +133,23 @@ impl error::Error for Error {
             Error::OddLengthString(_) => "odd hex string length",
             Error::Protobuf(_) => "encode/decode proto message failure",
             Error::Signing(_) => "signing error",
+            Error::Send(_) => "send message failure",
+            Error::Receive(_) => "receive message failure",
+            Error::Response(_) => "unexpected response",
+            Error::Http(_) => "http failure",
+            Error::Api(_) => "rest api error",
+            Error::InvalidAddress(_) => "invalid address",
+            Error::InvalidPublicKey(_) => "invalid public key",
+            Error::InvalidTransactionId(_) => "invalid transaction id",
+            Error::InvalidTemplate(_) => "invalid template",
+            Error::MissingField(_) => "missing field",
+            Error::TooLarge(_, _) => "too large",
+            Error::Rejected(_) => "rejected transactions",
+            Error::Keystore(_) => "keystore failure",
+            Error::Io(_) => "io failure",
+            Error::KeyFormat(_) => "invalid key format",
+            Error::Derivation(_) => "key derivation failure",
+            Error::Timeout(_) => "timeout",
             /*Error::InvalidLength(_) => "invalid length",
             Error::InvalidVersion(_, _) => "invalid version",
             Error::BS58(_) => "base58 decode failure",
@@ -99,3 +159,8 @@ impl error::Error for Error {
 }
 
 pub type Result<T> = result::Result<T, Error>;
+
+/// Decodes the protobuf message of type `T` from `bytes`.
+pub(crate) fn parse<T: protobuf::Message>(bytes: &[u8]) -> Result<T> {
+    T::parse_from_bytes(bytes).map_err(Error::Protobuf)
+}
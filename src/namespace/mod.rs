@@ -45,6 +45,12 @@ pub fn is_address(test: &str) -> bool {
     test.len() == 70
 }
 
+/// Checks `test` is a hex namespace prefix, a full address, or any partial
+/// address in between, as accepted in transaction inputs and outputs.
+pub fn is_valid_address(test: &str) -> bool {
+    test.len() >= 6 && test.len() <= 70 && util::is_hex_str(test)
+}
+
 fn sawtooth_build_family(name: &str) -> &str {
     match name {
         "000000" | "settings" => "000000",
@@ -116,6 +122,12 @@ mod tests {
         assert_eq!("69e807", prefix("df.citizen.service"));
         assert_eq!("12515f", prefix("df.citizen.transfer"));
         assert_eq!("c6cd3c", prefix("df.citizen.samsara"));
+
+        assert!(is_valid_address("1cf126"));
+        assert!(is_valid_address(&address("1cf126", "a1")));
+        assert!(!is_valid_address("1cf12"));
+        assert!(!is_valid_address("1cf12x"));
+        assert!(!is_valid_address(&"0".repeat(72)));
     }
 }
 
@@ -0,0 +1,295 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Keys derived from one seed by BIP32, with the seed recoverable from a
+//! BIP39 mnemonic.
+//!
+//! ```ignore
+//! let phrase = hd::generate_mnemonic(24)?;
+//! let root = HdKey::from_mnemonic(&phrase, "")?;
+//! let signer = root.derive("m/44'/0'/0'/0/7")?.signer()?;
+//!
+//! // watch-only, without the private key.
+//! let account = root.derive("m/44'/0'/0'")?.public();
+//! let wallet = account.derive("0/7")?.wallet();
+//! ```
+
+use super::Signer;
+use crate::util::bytes_to_hex_str;
+use crate::wallet;
+use crate::{Error, Result};
+use bip32::{ChildNumber, DerivationPath, Prefix, XPrv, XPub};
+use bip39::Mnemonic;
+use std::str::FromStr;
+
+// ----------------------------------------------------------------------------
+
+fn derivation_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::Derivation(e.to_string())
+}
+
+/// Parses `path`, either absolute like `m/44'/0'` for a master key at
+/// `depth` 0, or relative like `0/7` for any key.
+fn parse_path(path: &str, depth: u8) -> Result<DerivationPath> {
+    if path == "m" || path.starts_with("m/") {
+        if depth > 0 {
+            return Err(Error::Derivation(format!(
+                "absolute path {} from a key at depth {}",
+                path, depth
+            )));
+        }
+        DerivationPath::from_str(path).map_err(derivation_error)
+    } else {
+        DerivationPath::from_str(&format!("m/{}", path)).map_err(derivation_error)
+    }
+}
+
+/// Generates a mnemonic of `word_count` English words, one of 12, 15, 18, 21
+/// and 24.
+pub fn generate_mnemonic(word_count: usize) -> Result<String> {
+    Mnemonic::generate(word_count)
+        .map(|x| x.to_string())
+        .map_err(derivation_error)
+}
+
+/// Returns the seed of the mnemonic `phrase`, checking its words and
+/// checksum.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<[u8; 64]> {
+    Mnemonic::parse_normalized(phrase)
+        .map(|x| x.to_seed_normalized(passphrase))
+        .map_err(derivation_error)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Extended private key.
+#[derive(Clone)]
+pub struct HdKey {
+    key: XPrv,
+}
+
+impl HdKey {
+    /// Creates the master key of `seed`, 16 to 64 bytes.
+    pub fn from_seed(seed: &[u8]) -> Result<Self> {
+        XPrv::new(seed)
+            .map(|key| HdKey { key })
+            .map_err(derivation_error)
+    }
+
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> Result<Self> {
+        HdKey::from_seed(&mnemonic_to_seed(phrase, passphrase)?)
+    }
+
+    /// Parses a key serialized as `xprv...`.
+    pub fn from_xprv(xprv: &str) -> Result<Self> {
+        XPrv::from_str(xprv)
+            .map(|key| HdKey { key })
+            .map_err(derivation_error)
+    }
+
+    pub fn to_xprv(&self) -> String {
+        self.key.to_string(Prefix::XPRV).to_string()
+    }
+
+    /// Derives the child at `index`, hardened if `hardened`.
+    pub fn child(&self, index: u32, hardened: bool) -> Result<Self> {
+        ChildNumber::new(index, hardened)
+            .and_then(|x| self.key.derive_child(x))
+            .map(|key| HdKey { key })
+            .map_err(derivation_error)
+    }
+
+    /// Derives the key at `path` below this key, where `'` marks hardened
+    /// indices: absolute like `m/44'/0'/0'/0/1` from the master key, or
+    /// relative like `0/1` from any key.
+    pub fn derive(&self, path: &str) -> Result<Self> {
+        let mut key = self.key.clone();
+        for x in parse_path(path, self.key.attrs().depth)?.iter() {
+            key = key.derive_child(x).map_err(derivation_error)?;
+        }
+        Ok(HdKey { key })
+    }
+
+    /// Returns the signer of this key.
+    pub fn signer(&self) -> Result<Signer> {
+        Signer::from_hex(&bytes_to_hex_str(&self.key.to_bytes()))
+    }
+
+    /// Returns the extended public key, which derives the public keys of the
+    /// non-hardened children.
+    pub fn public(&self) -> HdPublicKey {
+        HdPublicKey {
+            key: self.key.public_key(),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Extended public key, for computing the public keys and wallets of
+/// children without the private key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdPublicKey {
+    key: XPub,
+}
+
+impl HdPublicKey {
+    /// Parses a key serialized as `xpub...`.
+    pub fn from_xpub(xpub: &str) -> Result<Self> {
+        XPub::from_str(xpub)
+            .map(|key| HdPublicKey { key })
+            .map_err(derivation_error)
+    }
+
+    pub fn to_xpub(&self) -> String {
+        self.key.to_string(Prefix::XPUB)
+    }
+
+    /// Derives the non-hardened child at `index`.
+    pub fn child(&self, index: u32) -> Result<Self> {
+        ChildNumber::new(index, false)
+            .and_then(|x| self.key.derive_child(x))
+            .map(|key| HdPublicKey { key })
+            .map_err(derivation_error)
+    }
+
+    /// Derives the key at `path` below this key, as `HdKey::derive`.
+    /// Hardened indices need the private key and are errors.
+    pub fn derive(&self, path: &str) -> Result<Self> {
+        let mut key = self.key.clone();
+        for x in parse_path(path, self.key.attrs().depth)?.iter() {
+            key = key.derive_child(x).map_err(derivation_error)?;
+        }
+        Ok(HdPublicKey { key })
+    }
+
+    /// Returns the compressed public key in hex, as `Signer::get_public_key`.
+    pub fn public_key(&self) -> String {
+        bytes_to_hex_str(&self.key.to_bytes())
+    }
+
+    pub fn wallet(&self) -> String {
+        wallet::new(&self.key.to_bytes())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl Signer {
+    /// Creates the signer of the key at `path` derived from the mnemonic
+    /// `phrase`.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str, path: &str) -> Result<Self> {
+        HdKey::from_mnemonic(phrase, passphrase)?
+            .derive(path)?
+            .signer()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vector 1 of BIP32.
+    static SEED_HEX: &str = "000102030405060708090a0b0c0d0e0f";
+
+    #[test]
+    fn test_bip32() {
+        let seed = crate::util::hex_str_to_bytes(SEED_HEX).unwrap();
+        let root = HdKey::from_seed(&seed).unwrap();
+        assert_eq!(
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+            root.to_xprv()
+        );
+
+        let child = root.derive("m/0'/1/2'/2/1000000000").unwrap();
+        assert_eq!(
+            "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+            child.public().to_xpub()
+        );
+
+        let steps = root
+            .child(0, true)
+            .and_then(|x| x.child(1, false))
+            .and_then(|x| x.child(2, true))
+            .unwrap();
+        assert_eq!(root.derive("m/0'/1/2'").unwrap().to_xprv(), steps.to_xprv());
+
+        // watch-only derivation matches the signers.
+        let account = root.derive("m/44'/0'/0'").unwrap();
+        let xpub = HdPublicKey::from_xpub(&account.public().to_xpub()).unwrap();
+        for idx in 0..3 {
+            let signer = account
+                .derive(&format!("0/{}", idx))
+                .unwrap()
+                .signer()
+                .unwrap();
+            let public = xpub.child(0).and_then(|x| x.child(idx)).unwrap();
+            assert_eq!(xpub.derive(&format!("0/{}", idx)).unwrap(), public);
+            assert_eq!(signer.get_public_key().unwrap(), public.public_key());
+            assert_eq!(
+                wallet::from_hex(&public.public_key()).unwrap(),
+                public.wallet()
+            );
+        }
+
+        assert!(xpub.derive("0'").is_err());
+        assert!(xpub.derive("m/0").is_err());
+        assert!(account.derive("m/0/1").is_err());
+        assert_eq!(
+            root.derive("m/0/1").unwrap().to_xprv(),
+            root.derive("0/1").unwrap().to_xprv()
+        );
+        assert!(root.derive("0/x").is_err());
+        assert!(HdKey::from_xprv(&root.to_xprv()).is_ok());
+    }
+
+    #[test]
+    fn test_bip39() {
+        // test vector of the trezor reference implementation.
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = mnemonic_to_seed(phrase, "TREZOR").unwrap();
+        assert_eq!(
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            bytes_to_hex_str(&seed)
+        );
+        assert!(mnemonic_to_seed(&phrase.replace("about", "abandon"), "").is_err());
+
+        let phrase = generate_mnemonic(24).unwrap();
+        assert_eq!(24, phrase.split(' ').count());
+        assert!(generate_mnemonic(13).is_err());
+
+        let signer = Signer::from_mnemonic(&phrase, "", "m/44'/0'/0'/0/0").unwrap();
+        let again = HdKey::from_mnemonic(&phrase, "")
+            .and_then(|x| x.derive("m/44'/0'/0'/0/0"))
+            .and_then(|x| x.signer())
+            .unwrap();
+        assert_eq!(
+            signer.get_public_key().unwrap(),
+            again.get_public_key().unwrap()
+        );
+    }
+}
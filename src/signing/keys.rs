@@ -0,0 +1,198 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Key files in the layout of the sawtooth CLI: the private key in hex text
+//! in `<name>.priv` and the public key in `<name>.pub`.
+
+use super::Signer;
+use crate::{Error, Result};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// ----------------------------------------------------------------------------
+
+/// Directory of the keys of users, `~/.sawtooth/keys`.
+pub fn user_key_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| Path::new(&home).join(".sawtooth").join("keys"))
+}
+
+/// Directory of the keys of validators, `$SAWTOOTH_HOME/keys` if
+/// `SAWTOOTH_HOME` is set, or `/etc/sawtooth/keys`.
+pub fn validator_key_dir() -> PathBuf {
+    validator_key_dir_in(sawtooth_home().as_deref())
+}
+
+/// Directories searched by `Signer::from_key_name`, in order:
+/// `$SAWTOOTH_HOME/keys` if set, `~/.sawtooth/keys` and `/etc/sawtooth/keys`.
+pub fn key_dirs() -> Vec<PathBuf> {
+    key_dirs_in(sawtooth_home().as_deref())
+}
+
+fn sawtooth_home() -> Option<PathBuf> {
+    env::var_os("SAWTOOTH_HOME").map(PathBuf::from)
+}
+
+fn validator_key_dir_in(home: Option<&Path>) -> PathBuf {
+    match home {
+        Some(home) => home.join("keys"),
+        None => PathBuf::from("/etc/sawtooth/keys"),
+    }
+}
+
+fn key_dirs_in(home: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(home) = home {
+        dirs.push(home.join("keys"));
+    }
+    if let Some(dir) = user_key_dir() {
+        dirs.push(dir);
+    }
+    dirs.push(PathBuf::from("/etc/sawtooth/keys"));
+    dirs
+}
+
+fn find_key(name: &str, dirs: &[PathBuf]) -> Result<Signer> {
+    let file_name = format!("{}.priv", name);
+    match dirs
+        .iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|x| x.is_file())
+    {
+        Some(path) => Signer::from_key_file(path),
+        None => Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found in {:?}", file_name, dirs),
+        ))),
+    }
+}
+
+fn create_new(path: &Path, data: &str, mode: u32) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    let mut file = options.open(path).map_err(Error::Io)?;
+    file.write_all(data.as_bytes()).map_err(Error::Io)
+}
+
+// ----------------------------------------------------------------------------
+
+impl Signer {
+    /// Loads the private key in hex text from `path`. Surrounding whitespace,
+    /// e.g. the trailing newline written by the sawtooth CLI, is ignored.
+    pub fn from_key_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(Error::Io)?;
+        Signer::from_hex(text.trim())
+    }
+
+    /// Loads `<name>.priv` from the first of `key_dirs` containing it.
+    pub fn from_key_name(name: &str) -> Result<Self> {
+        find_key(name, &key_dirs())
+    }
+
+    /// Returns the private key in hex.
+    pub fn to_hex(&self) -> String {
+        crate::util::bytes_to_hex_str(&self.private_key_bytes())
+    }
+
+    /// Writes the private key in hex text to `path`, readable by the owner
+    /// only. Existing files are not overwritten.
+    pub fn write_key_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        create_new(path.as_ref(), &format!("{}\n", self.to_hex()), 0o600)
+    }
+
+    /// Writes `<name>.priv` and `<name>.pub` to `dir` like `sawtooth keygen`,
+    /// and returns the path of the private key.
+    pub fn save_key(&self, dir: &Path, name: &str) -> Result<PathBuf> {
+        let priv_path = dir.join(format!("{}.priv", name));
+        let pub_path = dir.join(format!("{}.pub", name));
+        if pub_path.exists() {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists", pub_path.display()),
+            )));
+        }
+
+        fs::create_dir_all(dir).map_err(Error::Io)?;
+        self.write_key_file(&priv_path)?;
+        create_new(&pub_path, &format!("{}\n", self.get_public_key()?), 0o644)?;
+        Ok(priv_path)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{signer, KEY_PRIV_HEX, KEY_PUB_HEX};
+    use crate::util::uuid;
+
+    #[test]
+    fn test_key_files() {
+        let home = env::temp_dir().join(format!("sawtk-{}", uuid()));
+        let dir = home.join("keys");
+
+        let signer = signer();
+        assert_eq!(KEY_PRIV_HEX, signer.to_hex());
+        let path = signer.save_key(&dir, "alice").unwrap();
+        assert!(signer.save_key(&dir, "alice").is_err());
+        assert_eq!(
+            format!("{}\n", KEY_PUB_HEX),
+            fs::read_to_string(dir.join("alice.pub")).unwrap()
+        );
+
+        fs::write(dir.join("bob.priv"), format!("  {}\r\n\n", KEY_PRIV_HEX)).unwrap();
+        let loaded = Signer::from_key_file(&path).unwrap();
+        assert_eq!(KEY_PUB_HEX, loaded.get_public_key().unwrap());
+
+        assert_eq!(dir, validator_key_dir_in(Some(&home)));
+        assert_eq!(
+            PathBuf::from("/etc/sawtooth/keys"),
+            validator_key_dir_in(None)
+        );
+        let dirs = key_dirs_in(Some(&home));
+        assert_eq!(dir, dirs[0]);
+        assert_eq!(dirs[1..], key_dirs_in(None)[..]);
+
+        let bob = find_key("bob", &dirs).unwrap();
+        assert_eq!(KEY_PUB_HEX, bob.get_public_key().unwrap());
+        match find_key(&uuid(), &dirs) {
+            Err(Error::Io(e)) => assert_eq!(io::ErrorKind::NotFound, e.kind()),
+            _ => panic!("missing key loaded"),
+        }
+
+        fs::remove_dir_all(&home).unwrap();
+    }
+}
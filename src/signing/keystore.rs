@@ -0,0 +1,320 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Private keys encrypted with a password.
+//!
+//! The key is encrypted with AES-256-GCM under a key derived from the
+//! password by scrypt, and kept in a JSON envelope with the public key and
+//! the wallet for identification:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "public_key": "02...",
+//!   "wallet": "1...",
+//!   "crypto": {
+//!     "cipher": "aes-256-gcm",
+//!     "ciphertext": "...",
+//!     "nonce": "...",
+//!     "kdf": "scrypt",
+//!     "kdfparams": { "log_n": 15, "r": 8, "p": 1, "salt": "..." }
+//!   }
+//! }
+//! ```
+
+use super::Signer;
+use crate::util::{bytes_to_hex_str, hex_str_to_bytes};
+use crate::wallet;
+use crate::{Error, Result};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::{Deserialize, Serialize};
+
+static VERSION: u32 = 1;
+static CIPHER: &str = "aes-256-gcm";
+static KDF: &str = "scrypt";
+
+/// Ceilings of `KdfParams` accepted, or a crafted keystore could make
+/// decrypting take huge memory or practically forever.
+static MAX_LOG_N: u8 = 20;
+static MAX_MEMORY: u64 = 1 << 30;
+static MAX_P: u32 = 16;
+
+// ----------------------------------------------------------------------------
+
+/// Cost of scrypt. The defaults take about a second on commodity machines.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScryptParams {
+    #[serde(flatten)]
+    pub params: KdfParams,
+    pub salt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Crypto {
+    pub cipher: String,
+    pub ciphertext: String,
+    pub nonce: String,
+    pub kdf: String,
+    pub kdfparams: ScryptParams,
+}
+
+/// Encrypted private key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub public_key: String,
+    pub wallet: String,
+    pub crypto: Crypto,
+}
+
+impl KdfParams {
+    /// Fails if the cost is above the ceilings: `log_n` 20, 1 GiB memory
+    /// (128 * `r` * 2^`log_n` bytes) and `p` 16.
+    pub fn check(&self) -> Result<()> {
+        let memory = (128 * u64::from(self.r)) << self.log_n.min(MAX_LOG_N);
+        if self.log_n > MAX_LOG_N || memory > MAX_MEMORY || self.p > MAX_P {
+            return Err(Error::Keystore(format!(
+                "kdf params log_n {}, r {}, p {} too costly",
+                self.log_n, self.r, self.p
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn keystore_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::Keystore(e.to_string())
+}
+
+fn hex_field(name: &str, value: &str) -> Result<Vec<u8>> {
+    hex_str_to_bytes(value).map_err(|e| Error::Keystore(format!("{} {}", name, e)))
+}
+
+fn derive_key(password: &str, params: &KdfParams, salt: &[u8]) -> Result<[u8; 32]> {
+    params.check()?;
+    let params =
+        scrypt::Params::new(params.log_n, params.r, params.p, 32).map_err(keystore_error)?;
+    let mut key = [0_u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key).map_err(keystore_error)?;
+    Ok(key)
+}
+
+impl Keystore {
+    /// Encrypts the private key of `signer` with `password`.
+    pub fn encrypt(signer: &Signer, password: &str, params: KdfParams) -> Result<Self> {
+        let public_key = signer.get_public_key()?;
+
+        let mut salt = [0_u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0_u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+
+        let key = derive_key(password, &params, &salt)?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(keystore_error)?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &signer.private_key_bytes(),
+                    aad: public_key.as_bytes(),
+                },
+            )
+            .map_err(keystore_error)?;
+
+        Ok(Keystore {
+            version: VERSION,
+            wallet: wallet::from_hex(&public_key)?,
+            public_key,
+            crypto: Crypto {
+                cipher: CIPHER.to_string(),
+                ciphertext: bytes_to_hex_str(&ciphertext),
+                nonce: bytes_to_hex_str(&nonce),
+                kdf: KDF.to_string(),
+                kdfparams: ScryptParams {
+                    params,
+                    salt: bytes_to_hex_str(&salt),
+                },
+            },
+        })
+    }
+
+    /// Decrypts the private key with `password`. A wrong password fails the
+    /// authentication of the ciphertext.
+    pub fn decrypt(&self, password: &str) -> Result<Signer> {
+        if self.version != VERSION {
+            return Err(Error::Keystore(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+
+        if self.crypto.cipher != CIPHER || self.crypto.kdf != KDF {
+            return Err(Error::Keystore(format!(
+                "unsupported cipher {} or kdf {}",
+                self.crypto.cipher, self.crypto.kdf
+            )));
+        }
+
+        let salt = hex_field("salt", &self.crypto.kdfparams.salt)?;
+        let nonce = hex_field("nonce", &self.crypto.nonce)?;
+        let ciphertext = hex_field("ciphertext", &self.crypto.ciphertext)?;
+        if nonce.len() != 12 {
+            return Err(Error::Keystore(format!(
+                "invalid nonce {}",
+                self.crypto.nonce
+            )));
+        }
+
+        let key = derive_key(password, &self.crypto.kdfparams.params, &salt)?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(keystore_error)?;
+        let plain = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.public_key.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Keystore("wrong password or corrupted keystore".to_string()))?;
+
+        let signer = Signer::from_hex(&bytes_to_hex_str(&plain))?;
+        if signer.get_public_key()? != self.public_key {
+            return Err(Error::Keystore(format!(
+                "public key {} does not match",
+                self.public_key
+            )));
+        }
+        Ok(signer)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(keystore_error)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(keystore_error)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+impl Signer {
+    /// Decrypts the keystore in `json` with `password`.
+    pub fn from_keystore(json: &str, password: &str) -> Result<Self> {
+        Keystore::from_json(json)?.decrypt(password)
+    }
+
+    /// Encrypts the private key with `password` into a keystore in JSON.
+    pub fn to_keystore(&self, password: &str) -> Result<String> {
+        Keystore::encrypt(self, password, KdfParams::default())?.to_json()
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{signer, KEY_PRIV_HEX, KEY_PUB_HEX};
+
+    fn params() -> KdfParams {
+        KdfParams {
+            log_n: 10,
+            ..KdfParams::default()
+        }
+    }
+
+    #[test]
+    fn test_keystore() {
+        let signer = signer();
+        let keystore = Keystore::encrypt(&signer, "secret", params()).unwrap();
+        assert_eq!(KEY_PUB_HEX, keystore.public_key);
+        assert_eq!(wallet::from_hex(KEY_PUB_HEX).unwrap(), keystore.wallet);
+        assert!(!keystore.crypto.ciphertext.contains(&KEY_PRIV_HEX[..16]));
+
+        let json = keystore.to_json().unwrap();
+        assert!(json.contains("\"log_n\": 10"));
+        let decrypted = Signer::from_keystore(&json, "secret").unwrap();
+        assert_eq!(
+            signer.sign(b"test").unwrap(),
+            decrypted.sign(b"test").unwrap()
+        );
+
+        match Signer::from_keystore(&json, "wrong") {
+            Err(Error::Keystore(_)) => {}
+            _ => panic!("wrong password accepted"),
+        }
+
+        // the public key is authenticated with the ciphertext.
+        let mut swapped = keystore.clone();
+        swapped.public_key =
+            "039c20a66b4ec7995391dbec1d8bb0e2c6e6fd63cd259ed5b877cb4ea98858cf6d".to_string();
+        assert!(swapped.decrypt("secret").is_err());
+
+        let mut costly = keystore.clone();
+        costly.crypto.kdfparams.params.log_n = 40;
+        match costly.decrypt("secret") {
+            Err(Error::Keystore(_)) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        costly.crypto.kdfparams.params = KdfParams {
+            log_n: 20,
+            r: 16,
+            p: 1,
+        };
+        assert!(costly.decrypt("secret").is_err());
+
+        let mut odd = keystore.clone();
+        odd.crypto.nonce = "zz".to_string();
+        match odd.decrypt("secret") {
+            Err(Error::Keystore(msg)) => assert!(msg.starts_with("nonce ")),
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+
+        let mut future = keystore;
+        future.version = 2;
+        assert!(future.decrypt("secret").is_err());
+        assert!(Signer::from_keystore("{}", "secret").is_err());
+    }
+}
@@ -24,12 +24,47 @@
 */
 
 use super::{Error, Result};
+use crate::util::is_public_key;
 use sawtooth_sdk::signing;
+use sawtooth_sdk::signing::secp256k1::Secp256k1PublicKey;
+use std::collections::HashMap;
+
+#[cfg(feature = "hd")]
+pub mod hd;
+mod keys;
+#[cfg(feature = "keystore")]
+mod keystore;
+#[cfg(feature = "pem")]
+mod pem;
+#[cfg(unix)]
+pub mod remote;
+
+#[cfg(feature = "hd")]
+pub use self::hd::{HdKey, HdPublicKey};
+pub use self::keys::{key_dirs, user_key_dir, validator_key_dir};
+#[cfg(feature = "keystore")]
+pub use self::keystore::{Crypto, KdfParams, Keystore, ScryptParams};
+#[cfg(feature = "pem")]
+pub use self::pem::KeyFormat;
+#[cfg(unix)]
+pub use self::remote::{RemoteSigner, SignerServer};
 
 static ALG_NAME: &'static str = "secp256k1";
 
 // ----------------------------------------------------------------------------
 
+/// Signing backend of `tx::Builder`, `tx::Batcher` and the like, so keys may
+/// be kept out of the process, e.g. by `RemoteSigner`.
+pub trait Sign {
+    /// Returns the compressed public key in hex.
+    fn get_public_key(&self) -> Result<String>;
+
+    /// Signs `message` and returns the signature in hex.
+    fn sign(&self, message: &[u8]) -> Result<String>;
+}
+
+// ----------------------------------------------------------------------------
+
 pub struct Signer {
     context: Box<dyn signing::Context>,
     key: Box<dyn signing::PrivateKey>,
@@ -68,6 +103,91 @@ impl Signer {
             })
         })
     }
+
+    /// Returns the raw bytes of the private key, e.g. for writing key files.
+    pub fn private_key_bytes(&self) -> Vec<u8> {
+        self.key.as_slice().to_vec()
+    }
+
+    /// Returns the verifier of the signatures made by this signer.
+    pub fn verifier(&self) -> Result<Verifier> {
+        Verifier::from_hex(&self.get_public_key()?)
+    }
+}
+
+impl Sign for Signer {
+    fn get_public_key(&self) -> Result<String> {
+        Signer::get_public_key(self)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<String> {
+        Signer::sign(self, message)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Verifies signatures made with the private key of a public key.
+pub struct Verifier {
+    context: Box<dyn signing::Context>,
+    public_key: String,
+    key: Secp256k1PublicKey,
+}
+
+impl Verifier {
+    /// Creates the verifier of `key`, a compressed public key in hex.
+    pub fn from_hex(key: &str) -> Result<Self> {
+        if !is_public_key(key) {
+            return Err(Error::InvalidPublicKey(key.to_string()));
+        }
+
+        let context = create_context()?;
+        Secp256k1PublicKey::from_hex(key)
+            .map(|x| Verifier {
+                context,
+                public_key: key.to_string(),
+                key: x,
+            })
+            .map_err(Error::Signing)
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Returns whether `signature` in hex is the signature of `message`.
+    /// Malformed signatures are errors.
+    pub fn verify(&self, signature: &str, message: &[u8]) -> Result<bool> {
+        self.context
+            .verify(signature, message, &self.key)
+            .map_err(Error::Signing)
+    }
+}
+
+/// Verifies `signature` of `message` made by `public_key`.
+pub fn verify(public_key: &str, signature: &str, message: &[u8]) -> Result<bool> {
+    Verifier::from_hex(public_key)?.verify(signature, message)
+}
+
+/// Verifies many `(public key, message, signature)` triples, creating one
+/// verifier per distinct key. Returns whether each one is valid, taking
+/// invalid keys and malformed signatures as invalid.
+pub fn verify_all(items: &[(&str, &[u8], &str)]) -> Vec<bool> {
+    let mut verifiers: HashMap<&str, Option<Verifier>> = HashMap::new();
+
+    items
+        .iter()
+        .map(|(public_key, message, signature)| {
+            let verifier = verifiers
+                .entry(public_key)
+                .or_insert_with(|| Verifier::from_hex(public_key).ok());
+
+            match verifier {
+                Some(x) => x.verify(signature, message).unwrap_or(false),
+                None => false,
+            }
+        })
+        .collect()
 }
 
 // ----------------------------------------------------------------------------
@@ -119,4 +239,39 @@ mod tests {
         let sign2 = signer2.sign(&String::from(MSG2).into_bytes()).unwrap();
         assert_eq!(sign2, MSG2_KEY2_SIG);
     }
+
+    #[test]
+    fn test_verifier() {
+        let verifier = Verifier::from_hex(KEY1_PUB_HEX).unwrap();
+        assert_eq!(KEY1_PUB_HEX, verifier.public_key());
+        assert!(verifier.verify(MSG1_KEY1_SIG, MSG1.as_bytes()).unwrap());
+        assert!(!verifier.verify(MSG1_KEY1_SIG, MSG2.as_bytes()).unwrap());
+        assert!(!verifier.verify(MSG2_KEY2_SIG, MSG2.as_bytes()).unwrap());
+        assert!(verifier.verify("zz", MSG1.as_bytes()).is_err());
+
+        let signer2 = Signer::from_hex(KEY2_PRIV_HEX).unwrap();
+        let sign = signer2.sign(MSG1.as_bytes()).unwrap();
+        assert!(signer2
+            .verifier()
+            .unwrap()
+            .verify(&sign, MSG1.as_bytes())
+            .unwrap());
+        assert!(verify(KEY2_PUB_HEX, MSG2_KEY2_SIG, MSG2.as_bytes()).unwrap());
+
+        match Verifier::from_hex(&KEY1_PUB_HEX[2..]) {
+            Err(Error::InvalidPublicKey(_)) => {}
+            _ => panic!("public key not checked"),
+        }
+
+        assert_eq!(
+            vec![true, true, false, false, false],
+            verify_all(&[
+                (KEY1_PUB_HEX, MSG1.as_bytes(), MSG1_KEY1_SIG),
+                (KEY2_PUB_HEX, MSG2.as_bytes(), MSG2_KEY2_SIG),
+                (KEY1_PUB_HEX, MSG2.as_bytes(), MSG1_KEY1_SIG),
+                ("00", MSG1.as_bytes(), MSG1_KEY1_SIG),
+                (KEY2_PUB_HEX, MSG2.as_bytes(), "zz"),
+            ])
+        );
+    }
 }
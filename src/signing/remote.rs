@@ -0,0 +1,347 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Loopback signing service over a Unix socket, a reference of `Sign`
+//! backends keeping keys out of the process.
+//!
+//! The protocol is line based. A request is `PUBLIC_KEY` or `SIGN <message
+//! in hex>`, and the response is `OK <public key or signature>` or
+//! `ERR <message>`.
+//!
+//! ```ignore
+//! // custody process
+//! let server = SignerServer::bind("/run/sawtk/signer.sock")?;
+//! server.serve(&Signer::from_key_name("alice")?)?;
+//!
+//! // client process
+//! let signer = RemoteSigner::connect("/run/sawtk/signer.sock")?;
+//! let tx = TransactionBuilder::new(&signer)...build()?;
+//! ```
+
+use super::Sign;
+use crate::util::{bytes_to_hex_str, hex_str_to_bytes, uuid};
+use crate::{Error, Result};
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+static PUBLIC_KEY: &str = "PUBLIC_KEY";
+static SIGN: &str = "SIGN";
+static OK: &str = "OK";
+static ERR: &str = "ERR";
+
+/// Longest line accepted from the peer, enough for the hex of any header.
+static MAX_LINE: usize = 1024 * 1024;
+
+/// Reads a line of at most `MAX_LINE` bytes and strips the line break.
+/// Returns `None` at the end of the stream.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>> {
+    let mut line = String::new();
+    let size = io::Read::take(reader, MAX_LINE as u64 + 1)
+        .read_line(&mut line)
+        .map_err(Error::Io)?;
+
+    if size == 0 {
+        return Ok(None);
+    }
+    if size > MAX_LINE {
+        return Err(Error::TooLarge("remote signer line".to_string(), size));
+    }
+
+    let len = line.trim_end_matches(&['\r', '\n'][..]).len();
+    line.truncate(len);
+    Ok(Some(line))
+}
+
+// ----------------------------------------------------------------------------
+
+/// `Sign` backed by a `SignerServer`. The public key is fetched once by
+/// `connect`.
+pub struct RemoteSigner {
+    path: PathBuf,
+    stream: Mutex<BufReader<UnixStream>>,
+    public_key: String,
+}
+
+impl RemoteSigner {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let stream = UnixStream::connect(path.as_ref()).map_err(Error::Io)?;
+        let mut signer = RemoteSigner {
+            path: path.as_ref().to_path_buf(),
+            stream: Mutex::new(BufReader::new(stream)),
+            public_key: String::new(),
+        };
+        signer.public_key = signer.request(PUBLIC_KEY)?;
+        Ok(signer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn request(&self, line: &str) -> Result<String> {
+        let mut stream = self
+            .stream
+            .lock()
+            .map_err(|_| Error::Response("remote signer poisoned".to_string()))?;
+
+        let writer = stream.get_mut();
+        writer
+            .write_all(format!("{}\n", line).as_bytes())
+            .map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)?;
+
+        let response = match read_line(&mut *stream)? {
+            Some(x) => x,
+            None => return Err(Error::Response("remote signer closed".to_string())),
+        };
+
+        match response.split_once(' ') {
+            Some((status, value)) if status == OK => Ok(value.to_string()),
+            Some((status, msg)) if status == ERR => Err(Error::Response(msg.to_string())),
+            _ => Err(Error::Response(format!("unexpected {}", response))),
+        }
+    }
+}
+
+impl Sign for RemoteSigner {
+    fn get_public_key(&self) -> Result<String> {
+        Ok(self.public_key.clone())
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<String> {
+        self.request(&format!("{} {}", SIGN, bytes_to_hex_str(message)))
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Serves the requests of `RemoteSigner`s with a `Sign`, e.g. a `Signer`.
+/// Connections are served one at a time.
+pub struct SignerServer {
+    path: PathBuf,
+    listener: UnixListener,
+}
+
+impl SignerServer {
+    /// Listens at `path`, replacing a stale socket file. Other existing
+    /// files are errors. The socket is accessible by the owner only: it is
+    /// bound in a new directory of the owner, restricted, and then linked to
+    /// `path`, so nobody connects before.
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if !meta.file_type().is_socket() => {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                )));
+            }
+            Ok(_) => {
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(Error::Io(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} is served already", path.display()),
+                    )));
+                }
+                fs::remove_file(&path).map_err(Error::Io)?;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::Io(e)),
+        }
+
+        let name = path
+            .file_name()
+            .ok_or_else(|| {
+                Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a file path", path.display()),
+                ))
+            })?
+            .to_string_lossy();
+        let dir = path.with_file_name(format!(".{}.{}", name, &uuid()[..8]));
+        DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(Error::Io)?;
+
+        let tmp = dir.join("sock");
+        let result = UnixListener::bind(&tmp).and_then(|listener| {
+            fs::set_permissions(&tmp, Permissions::from_mode(0o600))?;
+            fs::hard_link(&tmp, &path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_file(&tmp);
+        let _ = fs::remove_dir(&dir);
+
+        let listener = result.map_err(Error::Io)?;
+        Ok(SignerServer { path, listener })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serves connections with `signer` until accepting fails.
+    pub fn serve(&self, signer: &dyn Sign) -> Result<()> {
+        loop {
+            self.serve_one(signer)?;
+        }
+    }
+
+    /// Accepts one connection and serves it until closed.
+    pub fn serve_one(&self, signer: &dyn Sign) -> Result<()> {
+        let (stream, _) = self.listener.accept().map_err(Error::Io)?;
+        let mut writer = stream.try_clone().map_err(Error::Io)?;
+
+        let mut reader = BufReader::new(stream);
+        // an oversized line ends the connection too.
+        while let Ok(Some(line)) = read_line(&mut reader) {
+            let response = match handle(signer, &line) {
+                Ok(value) => format!("{} {}\n", OK, value),
+                Err(e) => format!("{} {}\n", ERR, e),
+            };
+
+            if writer.write_all(response.as_bytes()).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle(signer: &dyn Sign, line: &str) -> Result<String> {
+    let (cmd, arg) = match line.split_once(' ') {
+        Some((cmd, arg)) => (cmd, arg),
+        None => (line, ""),
+    };
+
+    if cmd == PUBLIC_KEY {
+        signer.get_public_key()
+    } else if cmd == SIGN {
+        signer.sign(&hex_str_to_bytes(arg)?)
+    } else {
+        Err(Error::Response(format!("unknown command {}", cmd)))
+    }
+}
+
+impl Drop for SignerServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{signer, transaction, KEY_PUB_HEX};
+    use crate::tx::{verify_batch, Batcher};
+    use crate::util::uuid;
+    use std::env;
+    use std::thread;
+
+    #[test]
+    fn test_remote_signer() {
+        let path = env::temp_dir().join(format!("sawtk-{}.sock", uuid()));
+        let server = SignerServer::bind(&path).unwrap();
+        let handle = thread::spawn(move || {
+            let signer = signer();
+            server.serve_one(&signer).unwrap();
+        });
+
+        let remote = RemoteSigner::connect(&path).unwrap();
+        assert_eq!(KEY_PUB_HEX, Sign::get_public_key(&remote).unwrap());
+
+        let local = signer();
+        assert_eq!(
+            local.sign(b"test").unwrap(),
+            Sign::sign(&remote, b"test").unwrap()
+        );
+        match remote.request("NOPE") {
+            Err(Error::Response(msg)) => assert!(msg.contains("unknown command")),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let tx = transaction(&remote, "n1");
+        let batch = Batcher::new(&remote).build(&[tx]).unwrap();
+        assert!(verify_batch(&batch).is_ok());
+
+        drop(remote);
+        handle.join().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_read_line() {
+        let mut reader = io::Cursor::new(b"OK abc\r\nERR x".to_vec());
+        assert_eq!(Some("OK abc".to_string()), read_line(&mut reader).unwrap());
+        assert_eq!(Some("ERR x".to_string()), read_line(&mut reader).unwrap());
+        assert_eq!(None, read_line(&mut reader).unwrap());
+
+        let mut reader = io::Cursor::new(vec![b'a'; MAX_LINE + 10]);
+        match read_line(&mut reader) {
+            Err(Error::TooLarge(_, size)) => assert_eq!(MAX_LINE + 1, size),
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_bind() {
+        let path = env::temp_dir().join(format!("sawtk-{}.sock", uuid()));
+        fs::write(&path, "data").unwrap();
+        match SignerServer::bind(&path) {
+            Err(Error::Io(e)) => assert_eq!(io::ErrorKind::AlreadyExists, e.kind()),
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        assert_eq!("data", fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        // a socket left behind by a dead server is replaced.
+        drop(UnixListener::bind(&path).unwrap());
+        let server = SignerServer::bind(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+
+        // the directory the socket is bound in is removed.
+        let prefix = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+        assert!(!fs::read_dir(env::temp_dir()).unwrap().any(|x| x
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(&prefix)));
+
+        assert!(UnixStream::connect(&path).is_ok());
+        match SignerServer::bind(&path) {
+            Err(Error::Io(e)) => assert_eq!(io::ErrorKind::AddrInUse, e.kind()),
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        drop(server);
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Fixtures shared by the unit tests.
+
+use crate::signing::{Sign, Signer};
+use crate::tx::TransactionBuilder;
+use sawtooth_sdk::messages::transaction::Transaction;
+
+pub static KEY_PRIV_HEX: &str = "2f1e7b7a130d7ba9da0068b3bb0ba1d79e7e77110302c9f746c3c2a63fe40088";
+pub static KEY_PUB_HEX: &str = "026a2c795a9776f75464aa3bda3534c3154a6e91b357b1181d3f515110f84b67c5";
+
+/// Returns the signer of `KEY_PRIV_HEX`.
+pub fn signer() -> Signer {
+    Signer::from_hex(KEY_PRIV_HEX).unwrap()
+}
+
+/// Returns a builder of intkey transactions reading `1cf126`, with a small
+/// payload.
+pub fn builder(signer: &dyn Sign) -> TransactionBuilder<'_> {
+    TransactionBuilder::new(signer)
+        .family("intkey", "1.0")
+        .payload_bytes(vec![1, 2, 3])
+        .input("1cf126")
+}
+
+/// Returns a transaction from `builder` with the given nonce.
+pub fn transaction(signer: &dyn Sign, nonce: &str) -> Transaction {
+    builder(signer).nonce(nonce).build().unwrap()
+}
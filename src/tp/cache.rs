@@ -0,0 +1,232 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+use super::{Result, States};
+use protobuf::{self, Message};
+use sawtooth_sdk::processor::handler::TransactionContext;
+use std::collections::{BTreeMap, HashSet};
+
+// -----------------------------------------------------------------------------
+
+enum Pending {
+    Set(Box<dyn Message>),
+    Delete,
+}
+
+/// Transactional view of the state.
+///
+/// Entries are loaded through the context on first access and cached. `set` and
+/// `delete` are buffered until `commit`, which writes everything back with one
+/// `set_state_entries` and one `delete_state_entries` call.
+pub struct StateCache<'a> {
+    ctx: &'a dyn TransactionContext,
+    states: States,
+    loaded: HashSet<String>,
+    pending: BTreeMap<String, Pending>,
+}
+
+impl<'a> StateCache<'a> {
+    pub fn new(ctx: &'a dyn TransactionContext) -> Self {
+        StateCache::with_states(ctx, States::from(Vec::new()))
+    }
+
+    /// Creates a cache seeded with already loaded `states`, e.g. the ones
+    /// passed to `Apply::apply`.
+    pub fn with_states(ctx: &'a dyn TransactionContext, states: States) -> Self {
+        let loaded = states.data.keys().cloned().collect();
+        StateCache {
+            ctx,
+            states,
+            loaded,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Loads all `addresses` not loaded yet with one `get_state_entries` call.
+    pub fn load(&mut self, addresses: &[String]) -> Result<()> {
+        let mut missing: Vec<String> = Vec::new();
+        for address in addresses {
+            if !self.loaded.contains(address) && !missing.contains(address) {
+                missing.push(address.clone());
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.ctx.get_state_entries(&missing)?;
+        self.states.data.extend(result);
+        self.loaded.extend(missing);
+        Ok(())
+    }
+
+    pub fn contains(&mut self, address: &str) -> Result<bool> {
+        match self.pending.get(address) {
+            Some(Pending::Set(_)) => Ok(true),
+            Some(Pending::Delete) => Ok(false),
+            None => {
+                self.load(&[address.to_string()])?;
+                Ok(self.states.contains(address))
+            }
+        }
+    }
+
+    /// Returns the message at `address`, including buffered changes.
+    pub fn get<T: protobuf::Message + Clone>(&mut self, address: &str) -> Result<Option<T>> {
+        match self.pending.get(address) {
+            Some(Pending::Set(msg)) => match msg.as_any().downcast_ref::<T>() {
+                Some(x) => Ok(Some(x.clone())),
+                None => {
+                    let bytes = msg.write_to_bytes()?;
+                    let mut ret = T::new();
+                    ret.merge_from_bytes(&bytes)?;
+                    Ok(Some(ret))
+                }
+            },
+            Some(Pending::Delete) => Ok(None),
+            None => {
+                if self.contains(address)? {
+                    self.states.get::<T>(address).map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Buffers `msg` to be set at `address`.
+    pub fn set<T: protobuf::Message>(&mut self, address: &str, msg: T) {
+        self.pending
+            .insert(address.to_string(), Pending::Set(Box::new(msg)));
+    }
+
+    /// Buffers the deletion of `address`.
+    pub fn delete(&mut self, address: &str) {
+        self.pending.insert(address.to_string(), Pending::Delete);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Discards all buffered changes.
+    pub fn rollback(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Writes all buffered changes back through the context.
+    pub fn commit(self) -> Result<()> {
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut deletes: Vec<String> = Vec::new();
+
+        for (address, pending) in self.pending {
+            match pending {
+                Pending::Set(msg) => {
+                    let bytes = msg.write_to_bytes()?;
+                    entries.push((address, bytes));
+                }
+                Pending::Delete => deletes.push(address),
+            }
+        }
+
+        if !entries.is_empty() {
+            self.ctx.set_state_entries(entries)?;
+        }
+
+        if !deletes.is_empty() {
+            self.ctx.delete_state_entries(&deletes)?;
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::tp::MockContext;
+
+    fn request(cmd: i32) -> TPRequest {
+        TPRequest {
+            cmd,
+            ..TPRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_state_cache() {
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+        let a3 = ns.make_address("a3");
+
+        let prefix = vec![ns.prefix().to_string()];
+        let ctx = MockContext::new(&prefix, &prefix);
+        ctx.insert(&a1, &request(1)).unwrap();
+        ctx.insert(&a2, &request(2)).unwrap();
+
+        let mut cache = StateCache::new(&ctx);
+        cache.load(&[a1.clone(), a2.clone(), a3.clone()]).unwrap();
+        assert_eq!(1, cache.get::<TPRequest>(&a1).unwrap().unwrap().cmd);
+        assert!(cache.get::<TPRequest>(&a3).unwrap().is_none());
+
+        cache.set(&a1, request(10));
+        cache.set(&a3, request(30));
+        cache.delete(&a2);
+        assert!(cache.is_dirty());
+        assert_eq!(10, cache.get::<TPRequest>(&a1).unwrap().unwrap().cmd);
+        assert!(cache.contains(&a3).unwrap());
+        assert!(!cache.contains(&a2).unwrap());
+        assert!(ctx.sets().is_empty());
+
+        cache.commit().unwrap();
+        assert_eq!(2, ctx.sets().len());
+        assert_eq!(vec![a2.clone()], ctx.deletes());
+        assert_eq!(10, ctx.get_message::<TPRequest>(&a1).unwrap().unwrap().cmd);
+        assert_eq!(30, ctx.get_message::<TPRequest>(&a3).unwrap().unwrap().cmd);
+        assert!(!ctx.contains(&a2));
+    }
+
+    #[test]
+    fn test_rollback() {
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+
+        let prefix = vec![ns.prefix().to_string()];
+        let ctx = MockContext::new(&prefix, &prefix);
+
+        let mut cache = StateCache::new(&ctx);
+        cache.set(&a1, request(1));
+        cache.rollback();
+        assert!(!cache.is_dirty());
+        cache.commit().unwrap();
+        assert!(ctx.sets().is_empty());
+    }
+}
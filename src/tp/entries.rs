@@ -0,0 +1,315 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Several keyed entries stored under one address.
+//!
+//! `namespace::address` keeps only part of the hash of a key, so different keys
+//! may share an address. Like the intkey and xo families, the entries of all
+//! keys at an address are kept in one `StateEntries` container sorted by key.
+
+use super::{get_state_entries, set_state_entry, to_message, Result, StateCache};
+use crate::messages::state::{StateEntries, StateEntry};
+use crate::namespace::Namespace;
+use protobuf::{self, Message};
+use sawtooth_sdk::processor::handler::TransactionContext;
+
+// -----------------------------------------------------------------------------
+
+impl StateEntries {
+    /// Sorts the entries by key, as required by `find`, `put` and `remove`.
+    /// Containers written by other clients may not be sorted.
+    pub fn sort(&mut self) {
+        self.entries.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+
+    pub fn find(&self, key: &str) -> Option<&StateEntry> {
+        self.entries
+            .binary_search_by(|x| x.key.as_str().cmp(key))
+            .ok()
+            .map(|idx| &self.entries[idx])
+    }
+
+    /// Puts `data` of `key`, replacing the former one.
+    pub fn put(&mut self, key: &str, data: Vec<u8>) {
+        match self.entries.binary_search_by(|x| x.key.as_str().cmp(key)) {
+            Ok(idx) => self.entries[idx].data = data,
+            Err(idx) => self.entries.insert(
+                idx,
+                StateEntry {
+                    key: key.to_string(),
+                    data,
+                    ..StateEntry::default()
+                },
+            ),
+        }
+    }
+
+    /// Removes the entry of `key` and returns whether it existed.
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self.entries.binary_search_by(|x| x.key.as_str().cmp(key)) {
+            Ok(idx) => {
+                self.entries.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+fn decode<T: protobuf::Message>(entry: Option<&StateEntry>) -> Result<Option<T>> {
+    match entry {
+        Some(x) => to_message::<T>(&x.data).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn encode(data: &dyn Message) -> Result<Vec<u8>> {
+    Ok(data.write_to_bytes()?)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the container at `address` sorted by key, or an empty one.
+pub fn get_entries(ctx: &dyn TransactionContext, address: &str) -> Result<StateEntries> {
+    let states = get_state_entries(ctx, vec![address.to_string()])?;
+    if states.contains(address) {
+        let mut entries = states.get::<StateEntries>(address)?;
+        entries.sort();
+        Ok(entries)
+    } else {
+        Ok(StateEntries::default())
+    }
+}
+
+pub fn get_entry<T: protobuf::Message>(
+    ctx: &dyn TransactionContext,
+    ns: &dyn Namespace,
+    key: &str,
+) -> Result<Option<T>> {
+    let entries = get_entries(ctx, &ns.make_address(key))?;
+    decode(entries.find(key))
+}
+
+pub fn set_entry(
+    ctx: &dyn TransactionContext,
+    ns: &dyn Namespace,
+    key: &str,
+    data: &dyn Message,
+) -> Result<()> {
+    let address = ns.make_address(key);
+    let mut entries = get_entries(ctx, &address)?;
+    entries.put(key, encode(data)?);
+    set_state_entry(ctx, address, &entries)
+}
+
+/// Removes the entry of `key`, and deletes the address when no entry is left.
+/// Returns whether the entry existed.
+pub fn delete_entry(ctx: &dyn TransactionContext, ns: &dyn Namespace, key: &str) -> Result<bool> {
+    let address = ns.make_address(key);
+    let mut entries = get_entries(ctx, &address)?;
+    if !entries.remove(key) {
+        return Ok(false);
+    }
+
+    if entries.entries.is_empty() {
+        ctx.delete_state_entry(&address)?;
+    } else {
+        set_state_entry(ctx, address, &entries)?;
+    }
+    Ok(true)
+}
+
+// -----------------------------------------------------------------------------
+
+impl<'a> StateCache<'a> {
+    fn get_entries(&mut self, address: &str) -> Result<Option<StateEntries>> {
+        let mut entries = self.get::<StateEntries>(address)?;
+        if let Some(x) = entries.as_mut() {
+            x.sort();
+        }
+        Ok(entries)
+    }
+
+    pub fn get_entry<T: protobuf::Message>(
+        &mut self,
+        ns: &dyn Namespace,
+        key: &str,
+    ) -> Result<Option<T>> {
+        let entries = self.get_entries(&ns.make_address(key))?.unwrap_or_default();
+        decode(entries.find(key))
+    }
+
+    pub fn set_entry(&mut self, ns: &dyn Namespace, key: &str, data: &dyn Message) -> Result<()> {
+        let address = ns.make_address(key);
+        let mut entries = self.get_entries(&address)?.unwrap_or_default();
+        entries.put(key, encode(data)?);
+        self.set(&address, entries);
+        Ok(())
+    }
+
+    /// Like `tp::delete_entry`, but buffered until `commit`.
+    pub fn delete_entry(&mut self, ns: &dyn Namespace, key: &str) -> Result<bool> {
+        let address = ns.make_address(key);
+        let mut entries = match self.get_entries(&address)? {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        if !entries.remove(key) {
+            return Ok(false);
+        }
+
+        if entries.entries.is_empty() {
+            self.delete(&address);
+        } else {
+            self.set(&address, entries);
+        }
+        Ok(true)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::tp::MockContext;
+
+    fn request(cmd: i32) -> TPRequest {
+        TPRequest {
+            cmd,
+            ..TPRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_state_entries() {
+        let mut entries = StateEntries::default();
+        entries.put("b", vec![2]);
+        entries.put("a", vec![1]);
+        entries.put("c", vec![3]);
+        entries.put("b", vec![20]);
+
+        let keys: Vec<&str> = entries.entries.iter().map(|x| x.key.as_str()).collect();
+        assert_eq!(vec!["a", "b", "c"], keys);
+        assert_eq!(vec![20], entries.find("b").unwrap().data);
+        assert!(entries.find("d").is_none());
+
+        assert!(entries.remove("a"));
+        assert!(!entries.remove("a"));
+        assert_eq!(2, entries.entries.len());
+    }
+
+    #[test]
+    fn test_unsorted_entries() {
+        let ns = namespace::new("intkey");
+        let prefix = vec![ns.prefix().to_string()];
+        let ctx = MockContext::new(&prefix, &prefix);
+
+        // written unsorted by another client.
+        let address = ns.make_address("x9");
+        let mut entries = StateEntries::default();
+        for (key, cmd) in [("x9", 9), ("a1", 1)].iter() {
+            entries.entries.push(StateEntry {
+                key: key.to_string(),
+                data: request(*cmd).write_to_bytes().unwrap(),
+                ..StateEntry::default()
+            });
+        }
+        ctx.insert(&address, &entries).unwrap();
+
+        let found = get_entry::<TPRequest>(&ctx, ns.as_ref(), "x9").unwrap();
+        assert_eq!(9, found.unwrap().cmd);
+
+        let mut cache = StateCache::new(&ctx);
+        cache.set_entry(ns.as_ref(), "x9", &request(10)).unwrap();
+        cache.commit().unwrap();
+        let stored = ctx.get_message::<StateEntries>(&address).unwrap().unwrap();
+        let keys: Vec<&str> = stored.entries.iter().map(|x| x.key.as_str()).collect();
+        assert_eq!(vec!["a1", "x9"], keys);
+    }
+
+    #[test]
+    fn test_entry() {
+        let ns = namespace::new("intkey");
+        let prefix = vec![ns.prefix().to_string()];
+        let ctx = MockContext::new(&prefix, &prefix);
+
+        // simulates a collision by storing two keys at the address of "a1".
+        let address = ns.make_address("a1");
+        let mut entries = StateEntries::default();
+        entries.put("a1", request(1).write_to_bytes().unwrap());
+        entries.put("x9", request(9).write_to_bytes().unwrap());
+        ctx.insert(&address, &entries).unwrap();
+
+        assert_eq!(
+            1,
+            get_entry::<TPRequest>(&ctx, ns.as_ref(), "a1")
+                .unwrap()
+                .unwrap()
+                .cmd
+        );
+        assert!(get_entry::<TPRequest>(&ctx, ns.as_ref(), "a2")
+            .unwrap()
+            .is_none());
+
+        set_entry(&ctx, ns.as_ref(), "a1", &request(10)).unwrap();
+        let stored = ctx.get_message::<StateEntries>(&address).unwrap().unwrap();
+        assert_eq!(2, stored.entries.len());
+        assert_eq!(
+            10,
+            get_entry::<TPRequest>(&ctx, ns.as_ref(), "a1")
+                .unwrap()
+                .unwrap()
+                .cmd
+        );
+
+        assert!(delete_entry(&ctx, ns.as_ref(), "a1").unwrap());
+        assert!(!delete_entry(&ctx, ns.as_ref(), "a1").unwrap());
+        assert!(ctx.contains(&address));
+
+        let b1 = ns.make_address("b1");
+        let mut cache = StateCache::new(&ctx);
+        cache.set_entry(ns.as_ref(), "b1", &request(5)).unwrap();
+        assert_eq!(
+            5,
+            cache
+                .get_entry::<TPRequest>(ns.as_ref(), "b1")
+                .unwrap()
+                .unwrap()
+                .cmd
+        );
+        cache.commit().unwrap();
+        assert!(ctx.contains(&b1));
+
+        let mut cache = StateCache::new(&ctx);
+        assert!(cache.delete_entry(ns.as_ref(), "b1").unwrap());
+        assert!(!cache.delete_entry(ns.as_ref(), "b1").unwrap());
+        cache.commit().unwrap();
+        assert!(!ctx.contains(&b1));
+    }
+}
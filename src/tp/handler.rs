@@ -0,0 +1,269 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+use super::{get_state_entries, to_tp_request, Result, Router, States};
+use crate::messages::request::TPRequest;
+use crate::namespace::{is_address, Namespace};
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext, TransactionHandler};
+
+// -----------------------------------------------------------------------------
+
+/// Business logic of a transaction family driven by `Handler`.
+pub trait Apply {
+    /// Applies the decoded `req` signed by `signer`. `states` holds the entries
+    /// of all full addresses declared as inputs of the transaction, or none
+    /// if `prefetch` returns false.
+    fn apply(
+        &self,
+        req: TPRequest,
+        signer: &str,
+        states: States,
+        ctx: &dyn TransactionContext,
+    ) -> Result<()>;
+
+    /// Whether `Handler` reads the input addresses into `states` before
+    /// `apply`.
+    fn prefetch(&self) -> bool {
+        true
+    }
+}
+
+impl<F> Apply for F
+where
+    F: Fn(TPRequest, &str, States, &dyn TransactionContext) -> Result<()>,
+{
+    fn apply(
+        &self,
+        req: TPRequest,
+        signer: &str,
+        states: States,
+        ctx: &dyn TransactionContext,
+    ) -> Result<()> {
+        self(req, signer, states, ctx)
+    }
+}
+
+/// Dispatches `req` by its `cmd`. Handlers of the router read states from the
+/// context themselves, so nothing is prefetched.
+impl Apply for Router {
+    fn apply(
+        &self,
+        req: TPRequest,
+        signer: &str,
+        _states: States,
+        ctx: &dyn TransactionContext,
+    ) -> Result<()> {
+        self.dispatch(ctx, signer, &req)
+    }
+
+    fn prefetch(&self) -> bool {
+        false
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// `TransactionHandler` for a family whose payloads are `TPRequest`s.
+pub struct Handler<A: Apply> {
+    family_name: String,
+    family_versions: Vec<String>,
+    namespace: Box<dyn Namespace>,
+    app: A,
+}
+
+impl<A: Apply> Handler<A> {
+    pub fn new(
+        family_name: &str,
+        family_versions: &[&str],
+        namespace: Box<dyn Namespace>,
+        app: A,
+    ) -> Self {
+        Handler {
+            family_name: family_name.to_string(),
+            family_versions: family_versions.iter().map(|v| v.to_string()).collect(),
+            namespace,
+            app,
+        }
+    }
+
+    pub fn namespace(&self) -> &dyn Namespace {
+        self.namespace.as_ref()
+    }
+}
+
+impl<A: Apply> TransactionHandler for Handler<A> {
+    fn family_name(&self) -> String {
+        self.family_name.clone()
+    }
+
+    fn family_versions(&self) -> Vec<String> {
+        self.family_versions.clone()
+    }
+
+    fn namespaces(&self) -> Vec<String> {
+        vec![self.namespace.prefix().to_string()]
+    }
+
+    fn apply(
+        &self,
+        request: &TpProcessRequest,
+        context: &mut dyn TransactionContext,
+    ) -> std::result::Result<(), ApplyError> {
+        let req = to_tp_request(request)?;
+        let header = request.get_header();
+
+        let addresses: Vec<String> = header
+            .get_inputs()
+            .iter()
+            .filter(|x| is_address(x))
+            .cloned()
+            .collect();
+
+        let states = if addresses.is_empty() || !self.app.prefetch() {
+            States::from(Vec::new())
+        } else {
+            get_state_entries(context, addresses)?
+        };
+
+        Ok(self
+            .app
+            .apply(req, header.get_signer_public_key(), states, context)?)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::namespace;
+    use crate::tp::MockContext;
+    use protobuf::{Message, RepeatedField};
+    use sawtooth_sdk::messages::transaction::TransactionHeader;
+
+    #[test]
+    fn test_handler() {
+        let handler = Handler::new(
+            "intkey",
+            &["1.0", "1.1"],
+            namespace::new("intkey"),
+            |_: TPRequest, _: &str, _: States, _: &dyn TransactionContext| Ok(()),
+        );
+
+        assert_eq!("intkey", handler.family_name());
+        assert_eq!(vec!["1.0", "1.1"], handler.family_versions());
+        assert_eq!(vec!["1cf126"], handler.namespaces());
+        assert_eq!("intkey", handler.namespace().name());
+    }
+
+    #[test]
+    fn test_apply() {
+        let ns = namespace::new("intkey");
+        let address = ns.make_address("a1");
+
+        let handler = Handler::new(
+            "intkey",
+            &["1.0"],
+            namespace::new("intkey"),
+            |req: TPRequest, signer: &str, states: States, _: &dyn TransactionContext| {
+                assert_eq!(7, req.cmd);
+                assert_eq!("signer", signer);
+                let stored =
+                    states.get::<TPRequest>(&namespace::new("intkey").make_address("a1"))?;
+                assert_eq!(1, stored.cmd);
+                Ok(())
+            },
+        );
+
+        let mut header = TransactionHeader::default();
+        header.set_signer_public_key("signer".to_string());
+        header.set_inputs(RepeatedField::from_vec(vec![
+            ns.prefix().to_string(),
+            address.clone(),
+        ]));
+
+        let mut ctx = MockContext::from_header(&header);
+        ctx.insert(
+            &address,
+            &TPRequest {
+                cmd: 1,
+                ..TPRequest::default()
+            },
+        )
+        .unwrap();
+
+        let payload = TPRequest {
+            cmd: 7,
+            ..TPRequest::default()
+        };
+        let mut request = TpProcessRequest::default();
+        request.set_header(header);
+        request.set_payload(payload.write_to_bytes().unwrap());
+        handler.apply(&request, &mut ctx).unwrap();
+
+        request.set_payload(vec![0xff]);
+        assert!(handler.apply(&request, &mut ctx).is_err());
+    }
+
+    #[test]
+    fn test_router() {
+        let router = Router::new().route::<TPRequest, _>(7, |_, signer, msg| {
+            assert_eq!("signer", signer);
+            assert_eq!(1, msg.cmd);
+            Ok(())
+        });
+        let handler = Handler::new("intkey", &["1.0"], namespace::new("intkey"), router);
+
+        let mut header = TransactionHeader::default();
+        header.set_signer_public_key("signer".to_string());
+        // reading the input would fail, so the router must not prefetch it.
+        header.set_inputs(RepeatedField::from_vec(vec![
+            namespace::new("intkey").make_address("a1")
+        ]));
+        let mut ctx = MockContext::new(&[], &[]);
+
+        let inner = TPRequest {
+            cmd: 1,
+            ..TPRequest::default()
+        };
+        let mut payload = TPRequest {
+            cmd: 7,
+            payload: inner.write_to_bytes().unwrap(),
+            ..TPRequest::default()
+        };
+        let mut request = TpProcessRequest::default();
+        request.set_header(header);
+        request.set_payload(payload.write_to_bytes().unwrap());
+        handler.apply(&request, &mut ctx).unwrap();
+
+        payload.cmd = 8;
+        request.set_payload(payload.write_to_bytes().unwrap());
+        match handler.apply(&request, &mut ctx) {
+            Err(ApplyError::InvalidTransaction(_)) => {}
+            x => panic!("unexpected result {:?}", x),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+use super::{get_state_entries, set_state_entry, Result, StateCache, States};
+use crate::namespace::Namespace;
+use sawtooth_sdk::processor::handler::TransactionContext;
+use std::fmt;
+use std::marker::PhantomData;
+
+// -----------------------------------------------------------------------------
+
+/// State address bound to the message type stored at it.
+pub struct StateKey<T: protobuf::Message> {
+    address: String,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: protobuf::Message> StateKey<T> {
+    /// Creates the key of `key` in namespace `ns`.
+    pub fn new(ns: &dyn Namespace, key: &str) -> Self {
+        StateKey::from_address(ns.make_address(key))
+    }
+
+    pub fn from_address(address: String) -> Self {
+        StateKey {
+            address,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn get(&self, ctx: &dyn TransactionContext) -> Result<Option<T>> {
+        let states = get_state_entries(ctx, vec![self.address.clone()])?;
+        states.get_key(self)
+    }
+
+    pub fn set(&self, ctx: &dyn TransactionContext, msg: &T) -> Result<()> {
+        set_state_entry(ctx, self.address.clone(), msg)
+    }
+
+    /// Deletes the entry and returns whether it existed.
+    pub fn delete(&self, ctx: &dyn TransactionContext) -> Result<bool> {
+        Ok(ctx.delete_state_entry(&self.address)?.is_some())
+    }
+}
+
+impl<T: protobuf::Message> Clone for StateKey<T> {
+    fn clone(&self) -> Self {
+        StateKey::from_address(self.address.clone())
+    }
+}
+
+impl<T: protobuf::Message> fmt::Debug for StateKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("StateKey").field(&self.address).finish()
+    }
+}
+
+impl<T: protobuf::Message> fmt::Display for StateKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.address)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+impl States {
+    pub fn get_key<T: protobuf::Message>(&self, key: &StateKey<T>) -> Result<Option<T>> {
+        if self.contains(key.address()) {
+            self.get::<T>(key.address()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'a> StateCache<'a> {
+    pub fn get_key<T: protobuf::Message + Clone>(
+        &mut self,
+        key: &StateKey<T>,
+    ) -> Result<Option<T>> {
+        self.get::<T>(key.address())
+    }
+
+    pub fn set_key<T: protobuf::Message>(&mut self, key: &StateKey<T>, msg: T) {
+        self.set(key.address(), msg)
+    }
+
+    pub fn delete_key<T: protobuf::Message>(&mut self, key: &StateKey<T>) {
+        self.delete(key.address())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::tp::MockContext;
+
+    #[test]
+    fn test_state_key() {
+        let ns = namespace::new("intkey");
+        let key: StateKey<TPRequest> = StateKey::new(ns.as_ref(), "a1");
+        assert_eq!(ns.make_address("a1"), key.address());
+
+        let prefix = vec![ns.prefix().to_string()];
+        let ctx = MockContext::new(&prefix, &prefix);
+        assert!(key.get(&ctx).unwrap().is_none());
+
+        let msg = TPRequest {
+            cmd: 1,
+            ..TPRequest::default()
+        };
+        key.set(&ctx, &msg).unwrap();
+        assert_eq!(1, key.get(&ctx).unwrap().unwrap().cmd);
+
+        let mut cache = StateCache::new(&ctx);
+        assert_eq!(1, cache.get_key(&key).unwrap().unwrap().cmd);
+        cache.delete_key(&key);
+        assert!(cache.get_key(&key).unwrap().is_none());
+        cache.commit().unwrap();
+
+        assert!(!key.delete(&ctx).unwrap());
+    }
+}
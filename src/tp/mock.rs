@@ -0,0 +1,266 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+use super::to_message;
+use crate::namespace::is_address;
+use protobuf::{self, Message, RepeatedField};
+use sawtooth_sdk::messages::events::{Event, Event_Attribute};
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// -----------------------------------------------------------------------------
+
+/// In-memory `TransactionContext` for unit tests of transaction processors.
+///
+/// Like the validator, reads are only allowed under the declared inputs and
+/// writes and deletes under the declared outputs, where every input or output
+/// may be a full address or an address prefix.
+#[derive(Default)]
+pub struct MockContext {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    state: RefCell<HashMap<String, Vec<u8>>>,
+    sets: RefCell<Vec<(String, Vec<u8>)>>,
+    deletes: RefCell<Vec<String>>,
+    events: RefCell<Vec<Event>>,
+    receipts: RefCell<Vec<Vec<u8>>>,
+}
+
+impl MockContext {
+    pub fn new(inputs: &[String], outputs: &[String]) -> Self {
+        MockContext {
+            inputs: Vec::from(inputs),
+            outputs: Vec::from(outputs),
+            ..MockContext::default()
+        }
+    }
+
+    /// Creates a context restricted to the inputs and outputs of `header`.
+    pub fn from_header(header: &TransactionHeader) -> Self {
+        MockContext::new(header.get_inputs(), header.get_outputs())
+    }
+
+    /// Puts `data` at `address` without checking the outputs and without
+    /// recording it, for preparing the state before a test.
+    pub fn insert(&self, address: &str, data: &dyn Message) -> super::Result<()> {
+        let bytes = data.write_to_bytes()?;
+        self.state.borrow_mut().insert(address.to_string(), bytes);
+        Ok(())
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.state.borrow().contains_key(address)
+    }
+
+    pub fn get(&self, address: &str) -> Option<Vec<u8>> {
+        self.state.borrow().get(address).cloned()
+    }
+
+    pub fn get_message<T: protobuf::Message>(&self, address: &str) -> super::Result<Option<T>> {
+        match self.get(address) {
+            Some(bytes) => to_message::<T>(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Current state.
+    pub fn state(&self) -> HashMap<String, Vec<u8>> {
+        self.state.borrow().clone()
+    }
+
+    /// Entries set by the transaction processor, in order.
+    pub fn sets(&self) -> Vec<(String, Vec<u8>)> {
+        self.sets.borrow().clone()
+    }
+
+    /// Addresses deleted by the transaction processor, in order.
+    pub fn deletes(&self) -> Vec<String> {
+        self.deletes.borrow().clone()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.borrow().clone()
+    }
+
+    pub fn receipts(&self) -> Vec<Vec<u8>> {
+        self.receipts.borrow().clone()
+    }
+
+    fn check(&self, allowed: &[String], kind: &str, address: &str) -> Result<(), ContextError> {
+        if !is_address(address) {
+            return Err(ContextError::AuthorizationError(format!(
+                "invalid address {}",
+                address
+            )));
+        }
+
+        if allowed.iter().any(|x| address.starts_with(x.as_str())) {
+            Ok(())
+        } else {
+            Err(ContextError::AuthorizationError(format!(
+                "{} not in {}",
+                address, kind
+            )))
+        }
+    }
+}
+
+impl TransactionContext for MockContext {
+    fn get_state_entries(
+        &self,
+        addresses: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>, ContextError> {
+        for address in addresses {
+            self.check(&self.inputs, "inputs", address)?;
+        }
+
+        let state = self.state.borrow();
+        Ok(addresses
+            .iter()
+            .filter_map(|x| state.get(x).map(|v| (x.clone(), v.clone())))
+            .collect())
+    }
+
+    fn set_state_entries(&self, entries: Vec<(String, Vec<u8>)>) -> Result<(), ContextError> {
+        for (address, _) in entries.iter() {
+            self.check(&self.outputs, "outputs", address)?;
+        }
+
+        let mut state = self.state.borrow_mut();
+        for (address, data) in entries.iter() {
+            state.insert(address.clone(), data.clone());
+        }
+        self.sets.borrow_mut().extend(entries);
+        Ok(())
+    }
+
+    fn delete_state_entries(&self, addresses: &[String]) -> Result<Vec<String>, ContextError> {
+        for address in addresses {
+            self.check(&self.outputs, "outputs", address)?;
+        }
+
+        let mut state = self.state.borrow_mut();
+        let deleted: Vec<String> = addresses
+            .iter()
+            .filter(|x| state.remove(x.as_str()).is_some())
+            .cloned()
+            .collect();
+        self.deletes.borrow_mut().extend(deleted.iter().cloned());
+        Ok(deleted)
+    }
+
+    fn add_receipt_data(&self, data: &[u8]) -> Result<(), ContextError> {
+        self.receipts.borrow_mut().push(Vec::from(data));
+        Ok(())
+    }
+
+    fn add_event(
+        &self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        let attributes: Vec<Event_Attribute> = attributes
+            .into_iter()
+            .map(|(key, value)| Event_Attribute {
+                key,
+                value,
+                ..Event_Attribute::default()
+            })
+            .collect();
+
+        self.events.borrow_mut().push(Event {
+            event_type,
+            attributes: RepeatedField::from_vec(attributes),
+            data: Vec::from(data),
+            ..Event::default()
+        });
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::tp;
+
+    fn request(cmd: i32) -> TPRequest {
+        TPRequest {
+            cmd,
+            ..TPRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_mock_context() {
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+        let other = namespace::new("xo").make_address("a1");
+
+        let ctx = MockContext::new(&[ns.prefix().to_string()], std::slice::from_ref(&a1));
+        ctx.insert(&a2, &request(2)).unwrap();
+
+        let states = tp::get_state_entries(&ctx, vec![a1.clone(), a2.clone()]).unwrap();
+        assert!(!states.contains(&a1));
+        assert_eq!(2, states.get::<TPRequest>(&a2).unwrap().cmd);
+        assert!(tp::get_state_entries(&ctx, vec![other.clone()]).is_err());
+        assert!(tp::get_state_entries(&ctx, vec![ns.prefix().to_string()]).is_err());
+
+        tp::set_state_entry(&ctx, a1.clone(), &request(1)).unwrap();
+        assert!(tp::set_state_entry(&ctx, a2.clone(), &request(1)).is_err());
+        assert_eq!(1, ctx.get_message::<TPRequest>(&a1).unwrap().unwrap().cmd);
+        assert_eq!(1, ctx.sets().len());
+
+        assert_eq!(Some(a1.clone()), tp::delete_state_entry(&ctx, &a1).unwrap());
+        assert_eq!(None, tp::delete_state_entry(&ctx, &a1).unwrap());
+        assert!(tp::delete_state_entry(&ctx, &a2).is_err());
+        assert_eq!(vec![a1.clone()], ctx.deletes());
+        assert!(!ctx.contains(&a1));
+        assert!(ctx.contains(&a2));
+
+        tp::add_event(
+            &ctx,
+            "intkey/set".to_string(),
+            vec![("key".to_string(), "a1".to_string())],
+            &request(3),
+        )
+        .unwrap();
+        let events = ctx.events();
+        assert_eq!(1, events.len());
+        assert_eq!("intkey/set", events[0].event_type);
+        assert_eq!("a1", events[0].attributes[0].value);
+        assert_eq!(3, tp::to_message::<TPRequest>(&events[0].data).unwrap().cmd);
+
+        ctx.add_receipt_data(b"receipt").unwrap();
+        assert_eq!(vec![b"receipt".to_vec()], ctx.receipts());
+    }
+}
@@ -24,11 +24,13 @@
 */
 
 use crate::messages::request::TPRequest;
-use protobuf::{self, Message};
+use protobuf::{self, Message, ProtobufError};
 use sawtooth_sdk::messages::processor::TpProcessRequest;
 use sawtooth_sdk::processor::handler::ApplyError;
-use sawtooth_sdk::processor::handler::TransactionContext;
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext};
+use sawtooth_sdk::signing::Error as SignErr;
 use std::collections::HashMap;
+use std::{error, fmt, result};
 
 // -----------------------------------------------------------------------------
 
@@ -40,6 +42,137 @@ macro_rules! invalid_transaction {
         )
     }
 }
+
+mod cache;
+mod entries;
+mod handler;
+mod key;
+#[cfg(any(test, feature = "testing"))]
+mod mock;
+mod router;
+
+pub use self::cache::StateCache;
+pub use self::entries::{delete_entry, get_entries, get_entry, set_entry};
+pub use self::handler::{Apply, Handler};
+pub use self::key::StateKey;
+#[cfg(any(test, feature = "testing"))]
+pub use self::mock::MockContext;
+pub use self::router::Router;
+#[cfg(feature = "derive")]
+pub use sawtk_derive::validate;
+
+// -----------------------------------------------------------------------------
+
+/// Errors of transaction processing.
+///
+/// Converting into `ApplyError` decides whether the validator drops the
+/// transaction or retries it: `Internal` and failures of the context, except
+/// accessing addresses not declared by the transaction, become
+/// `InternalError`, and all others become `InvalidTransaction`.
+///
+/// Crate errors of transports, e.g. sending messages or HTTP requests, and of
+/// IO or signing become `Internal`; errors of malformed input, e.g. hex
+/// strings, addresses or keys, become `Invalid`.
+///
+/// `ApplyError`s, e.g. of `invalid_transaction!`, convert back with `?` or
+/// `into()`, keeping their kind.
+#[derive(Debug)]
+pub enum Error {
+    Protobuf(ProtobufError),
+    NotFound(String),
+    Invalid(String),
+    Context(ContextError),
+    Internal(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            Error::Protobuf(e) => write!(f, "encode/decode proto message {}", e),
+            Error::NotFound(address) => write!(f, "{} not found", address),
+            Error::Invalid(msg) => write!(f, "{}", msg),
+            Error::Context(e) => e.fmt(f),
+            Error::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self {
+            Error::Protobuf(ref e) => Some(e),
+            Error::Context(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProtobufError> for Error {
+    fn from(e: ProtobufError) -> Self {
+        Error::Protobuf(e)
+    }
+}
+
+impl From<ContextError> for Error {
+    fn from(e: ContextError) -> Self {
+        Error::Context(e)
+    }
+}
+
+impl From<ApplyError> for Error {
+    fn from(e: ApplyError) -> Self {
+        match e {
+            ApplyError::InvalidTransaction(msg) => Error::Invalid(msg),
+            ApplyError::InternalError(msg) => Error::Internal(msg),
+        }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(e: crate::Error) -> Self {
+        // no wildcard, so a new variant has to be classified here.
+        match e {
+            crate::Error::Protobuf(e) => Error::Protobuf(e),
+            e @ crate::Error::Signing(SignErr::ParseError(_))
+            | e @ crate::Error::InvalidChar(_, _)
+            | e @ crate::Error::OddLengthString(_)
+            | e @ crate::Error::InvalidAddress(_)
+            | e @ crate::Error::InvalidPublicKey(_)
+            | e @ crate::Error::InvalidTransactionId(_)
+            | e @ crate::Error::InvalidTemplate(_)
+            | e @ crate::Error::MissingField(_)
+            | e @ crate::Error::TooLarge(_, _)
+            | e @ crate::Error::Rejected(_)
+            | e @ crate::Error::Keystore(_)
+            | e @ crate::Error::KeyFormat(_)
+            | e @ crate::Error::Derivation(_) => Error::Invalid(e.to_string()),
+            e @ crate::Error::Signing(_)
+            | e @ crate::Error::Send(_)
+            | e @ crate::Error::Receive(_)
+            | e @ crate::Error::Response(_)
+            | e @ crate::Error::Http(_)
+            | e @ crate::Error::Api(_)
+            | e @ crate::Error::Io(_)
+            | e @ crate::Error::Timeout(_) => Error::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<Error> for ApplyError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Internal(msg) => ApplyError::InternalError(msg),
+            Error::Context(ContextError::AuthorizationError(_)) => {
+                ApplyError::InvalidTransaction(e.to_string())
+            }
+            Error::Context(_) => ApplyError::InternalError(e.to_string()),
+            _ => ApplyError::InvalidTransaction(e.to_string()),
+        }
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
 // -----------------------------------------------------------------------------
 
 pub struct States {
@@ -51,23 +184,18 @@ impl States {
         self.data.contains_key(address)
     }
 
-    pub fn get<T: protobuf::Message>(&self, address: &str) -> Result<T, ApplyError> {
+    pub fn get<T: protobuf::Message>(&self, address: &str) -> Result<T> {
         let mut msg = T::new();
         self.get_message(address, &mut msg)?;
         Ok(msg)
     }
 
-    pub fn get_message(
-        &self,
-        address: &str,
-        msg: &mut dyn protobuf::Message,
-    ) -> Result<(), ApplyError> {
+    pub fn get_message(&self, address: &str, msg: &mut dyn protobuf::Message) -> Result<()> {
         let bytes = self
             .data
             .get(address)
-            .ok_or(invalid_transaction!("{} not found", address))?;
-        msg.merge_from_bytes(bytes)
-            .map_err(|e| invalid_transaction!("{}", e))?;
+            .ok_or_else(|| Error::NotFound(address.to_string()))?;
+        msg.merge_from_bytes(bytes)?;
         Ok(())
     }
 }
@@ -85,66 +213,50 @@ impl From<Vec<(String, Vec<u8>)>> for States {
 
 // -----------------------------------------------------------------------------
 
-pub fn get_state_entries(
-    ctx: &dyn TransactionContext,
-    addresses: Vec<String>,
-) -> Result<States, ApplyError> {
-    let result = ctx
-        .get_state_entries(&addresses)
-        .map_err(|e| invalid_transaction!("{}", e))?;
+pub fn get_state_entries(ctx: &dyn TransactionContext, addresses: Vec<String>) -> Result<States> {
+    let result = ctx.get_state_entries(&addresses)?;
     Ok(States::from(result))
 }
 
 pub fn get_state_entry<T: protobuf::Message>(
     ctx: &dyn TransactionContext,
     address: &str,
-) -> Result<T, ApplyError> {
+) -> Result<T> {
     get_state_entries(ctx, vec![address.to_string()])?.get::<T>(address)
 }
 
 pub fn delete_state_entries(
     ctx: &dyn TransactionContext,
     addresses: &[String],
-) -> Result<Vec<String>, ApplyError> {
-    ctx.delete_state_entries(addresses)
-        .map_err(|e| invalid_transaction!("{}", e))
+) -> Result<Vec<String>> {
+    Ok(ctx.delete_state_entries(addresses)?)
 }
 
-pub fn delete_state_entry(
-    ctx: &dyn TransactionContext,
-    address: &str,
-) -> Result<Option<String>, ApplyError> {
-    ctx.delete_state_entry(address)
-        .map_err(|e| invalid_transaction!("{}", e))
+pub fn delete_state_entry(ctx: &dyn TransactionContext, address: &str) -> Result<Option<String>> {
+    Ok(ctx.delete_state_entry(address)?)
 }
 
 pub fn set_state_entries(
     ctx: &dyn TransactionContext,
     data: Vec<(String, &dyn Message)>,
-) -> Result<(), ApplyError> {
+) -> Result<()> {
     let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
 
     for rs in data {
-        let bytes =
-            rs.1.write_to_bytes()
-                .map_err(|e| invalid_transaction!("{}", e))?;
+        let bytes = rs.1.write_to_bytes()?;
         entries.push((rs.0, bytes));
     }
 
-    ctx.set_state_entries(entries)
-        .map_err(|e| invalid_transaction!("{}", e))
+    Ok(ctx.set_state_entries(entries)?)
 }
 
 pub fn set_state_entry(
     ctx: &dyn TransactionContext,
     address: String,
     data: &dyn Message,
-) -> Result<(), ApplyError> {
-    let bytes = data
-        .write_to_bytes()
-        .map_err(|e| invalid_transaction!("{}", e))?;
-    ctx.set_state_entry(address, bytes)
-        .map_err(|e| invalid_transaction!("{}", e))
+) -> Result<()> {
+    let bytes = data.write_to_bytes()?;
+    Ok(ctx.set_state_entry(address, bytes)?)
 }
 
 pub fn add_event(
@@ -152,31 +264,128 @@ pub fn add_event(
     event_type: String,
     attributes: Vec<(String, String)>,
     data: &dyn Message,
-) -> Result<(), ApplyError> {
-    let bytes = data
-        .write_to_bytes()
-        .map_err(|e| invalid_transaction!("{}", e))?;
-    ctx.add_event(event_type, attributes, &bytes)
-        .map_err(|e| invalid_transaction!("{}", e))
+) -> Result<()> {
+    let bytes = data.write_to_bytes()?;
+    Ok(ctx.add_event(event_type, attributes, &bytes)?)
+}
+
+/// Adds `data` to the receipt of the transaction, e.g. for returning computed
+/// results to clients.
+pub fn add_receipt_data(ctx: &dyn TransactionContext, data: &dyn Message) -> Result<()> {
+    let bytes = data.write_to_bytes()?;
+    Ok(ctx.add_receipt_data(&bytes)?)
 }
 
 // -----------------------------------------------------------------------------
 
-pub fn to_tp_request(req: &TpProcessRequest) -> Result<TPRequest, ApplyError> {
+pub fn to_tp_request(req: &TpProcessRequest) -> Result<TPRequest> {
     to_message::<TPRequest>(&req.payload)
 }
 
-pub fn to_message<T: protobuf::Message>(bytes: &[u8]) -> Result<T, ApplyError> {
-    protobuf::parse_from_bytes::<T>(bytes).map_err(|e| invalid_transaction!("{}", e))
+pub fn to_message<T: protobuf::Message>(bytes: &[u8]) -> Result<T> {
+    crate::parse::<T>(bytes).map_err(Error::from)
 }
 
 // -----------------------------------------------------------------------------
 pub trait Validate: protobuf::Message {
-    fn validate(&mut self) -> Result<(), ApplyError>;
+    fn validate(&mut self) -> Result<()>;
 }
 
 impl Validate for TPRequest {
-    fn validate(&mut self) -> Result<(), ApplyError> {
+    fn validate(&mut self) -> Result<()> {
         Ok(())
     }
 }
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_error() {
+        match ApplyError::from(Error::NotFound("addr".to_string())) {
+            ApplyError::InvalidTransaction(msg) => assert_eq!("addr not found", msg),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::Context(ContextError::AuthorizationError(
+            "denied".to_string(),
+        ))) {
+            ApplyError::InvalidTransaction(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::Context(ContextError::ResponseAttributeError(
+            "timeout".to_string(),
+        ))) {
+            ApplyError::InternalError(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match Error::from(invalid_transaction!("bad {}", 1)) {
+            Error::Invalid(msg) => assert_eq!("bad 1", msg),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::from(ApplyError::InternalError("retry".to_string()))) {
+            ApplyError::InternalError(msg) => assert_eq!("retry", msg),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match to_message::<TPRequest>(&[0xff]) {
+            Err(Error::Protobuf(_)) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_crate_error() {
+        match ApplyError::from(Error::from(crate::Error::InvalidAddress("a1".to_string()))) {
+            ApplyError::InvalidTransaction(msg) => assert_eq!("invalid address a1", msg),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::from(crate::Error::InvalidChar(1, 'x'))) {
+            ApplyError::InvalidTransaction(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::from(crate::Error::MissingField("payload"))) {
+            ApplyError::InvalidTransaction(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        match ApplyError::from(Error::from(crate::Error::Timeout(vec!["b1".to_string()]))) {
+            ApplyError::InternalError(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let io = std::io::Error::other("broken pipe");
+        match ApplyError::from(Error::from(crate::Error::Io(io))) {
+            ApplyError::InternalError(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let parse = SignErr::ParseError("bad key".to_string());
+        match ApplyError::from(Error::from(crate::Error::Signing(parse))) {
+            ApplyError::InvalidTransaction(_) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_add_receipt_data() {
+        let ctx = MockContext::default();
+        let msg = TPRequest {
+            cmd: 1,
+            ..TPRequest::default()
+        };
+        add_receipt_data(&ctx, &msg).unwrap();
+
+        let receipts = ctx.receipts();
+        assert_eq!(1, receipts.len());
+        assert_eq!(1, to_message::<TPRequest>(&receipts[0]).unwrap().cmd);
+    }
+}
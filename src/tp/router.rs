@@ -0,0 +1,139 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+use super::{to_message, to_tp_request, Error, Result, Validate};
+use crate::messages::request::TPRequest;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::processor::handler::TransactionContext;
+use std::collections::HashMap;
+
+// -----------------------------------------------------------------------------
+
+type Route = Box<dyn Fn(&dyn TransactionContext, &str, &[u8]) -> Result<()> + Send + Sync>;
+
+/// Routes a `TPRequest` to the handler registered for its `cmd`.
+///
+/// The payload is decoded into the message type of the handler and validated
+/// before the handler is called.
+#[derive(Default)]
+pub struct Router {
+    routes: HashMap<i32, Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// Registers `handler` for `cmd`. A later registration of the same `cmd`
+    /// replaces the former one.
+    pub fn route<T, F>(mut self, cmd: i32, handler: F) -> Self
+    where
+        T: Validate,
+        F: Fn(&dyn TransactionContext, &str, T) -> Result<()> + Send + Sync + 'static,
+    {
+        self.routes.insert(
+            cmd,
+            Box::new(move |ctx, signer, payload| {
+                let mut msg = to_message::<T>(payload)?;
+                msg.validate()?;
+                handler(ctx, signer, msg)
+            }),
+        );
+        self
+    }
+
+    pub fn contains(&self, cmd: i32) -> bool {
+        self.routes.contains_key(&cmd)
+    }
+
+    /// Calls the handler of `req.cmd` with the signer public key of the transaction.
+    pub fn dispatch(
+        &self,
+        ctx: &dyn TransactionContext,
+        signer: &str,
+        req: &TPRequest,
+    ) -> Result<()> {
+        let route = self
+            .routes
+            .get(&req.cmd)
+            .ok_or_else(|| Error::Invalid(format!("unknown command {}", req.cmd)))?;
+        route(ctx, signer, &req.payload)
+    }
+
+    /// Decodes the `TPRequest` envelope of `request` and dispatches it.
+    pub fn apply(&self, request: &TpProcessRequest, ctx: &dyn TransactionContext) -> Result<()> {
+        let req = to_tp_request(request)?;
+        self.dispatch(ctx, request.get_header().get_signer_public_key(), &req)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tp::MockContext;
+    use protobuf::Message;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::sync::Arc;
+
+    fn request(cmd: i32, payload: Vec<u8>) -> TPRequest {
+        TPRequest {
+            cmd,
+            payload,
+            ..TPRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let called = Arc::new(AtomicI32::new(0));
+        let inner = called.clone();
+
+        let router = Router::new().route::<TPRequest, _>(1, move |_, signer, msg| {
+            assert_eq!("signer", signer);
+            inner.store(msg.cmd, Ordering::SeqCst);
+            Ok(())
+        });
+        assert!(router.contains(1));
+        assert!(!router.contains(2));
+
+        let ctx = MockContext::default();
+        let inner = request(99, Vec::new()).write_to_bytes().unwrap();
+        router.dispatch(&ctx, "signer", &request(1, inner)).unwrap();
+        assert_eq!(99, called.load(Ordering::SeqCst));
+
+        match router.dispatch(&ctx, "signer", &request(2, Vec::new())) {
+            Err(Error::Invalid(_)) => {}
+            x => panic!("unexpected result {:?}", x),
+        }
+
+        match router.dispatch(&ctx, "signer", &request(1, vec![0xff])) {
+            Err(Error::Protobuf(_)) => {}
+            x => panic!("unexpected result {:?}", x),
+        }
+    }
+}
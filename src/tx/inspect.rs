@@ -0,0 +1,343 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Decoding of transactions and batches for debugging.
+//!
+//! ```ignore
+//! println!("{}", inspect_batch(&batch).to_text());
+//! ```
+
+use crate::messages::request::TPRequest;
+use crate::util::bytes_to_hex_str;
+use crate::{parse, Result};
+use sawtooth_sdk::messages::batch::{Batch, BatchHeader, BatchList};
+use sawtooth_sdk::messages::transaction::{Transaction, TransactionHeader};
+#[cfg(feature = "json")]
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+// ----------------------------------------------------------------------------
+
+pub fn transaction_header(tx: &Transaction) -> Result<TransactionHeader> {
+    parse::<TransactionHeader>(&tx.header)
+}
+
+pub fn batch_header(batch: &Batch) -> Result<BatchHeader> {
+    parse::<BatchHeader>(&batch.header)
+}
+
+/// Decodes the payload of `tx` as a `TPRequest`.
+pub fn tp_request(tx: &Transaction) -> Result<TPRequest> {
+    parse::<TPRequest>(&tx.payload)
+}
+
+// ----------------------------------------------------------------------------
+
+/// Decoded tree of a transaction, a batch or a batch list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Text(String),
+    Number(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Node>),
+    Fields(Vec<(String, Node)>),
+}
+
+fn text(s: &str) -> Node {
+    Node::Text(s.to_string())
+}
+
+fn texts(list: &[String]) -> Node {
+    Node::List(list.iter().map(|x| text(x)).collect())
+}
+
+fn fields(list: Vec<(&str, Node)>) -> Node {
+    Node::Fields(list.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+impl Node {
+    /// Renders as indented `key: value` lines. Bytes are shown in hex and
+    /// base64.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out, 0);
+        out
+    }
+
+    fn write_text(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent);
+        match self {
+            Node::Fields(list) => {
+                for (key, value) in list {
+                    match value {
+                        Node::Fields(_) | Node::List(_) => {
+                            let _ = writeln!(out, "{}{}:", pad, key);
+                            value.write_text(out, indent + 1);
+                        }
+                        x => {
+                            let _ = writeln!(out, "{}{}: {}", pad, key, x.scalar());
+                        }
+                    }
+                }
+            }
+            Node::List(list) => {
+                for (idx, value) in list.iter().enumerate() {
+                    match value {
+                        Node::Fields(_) | Node::List(_) => {
+                            let _ = writeln!(out, "{}[{}]", pad, idx);
+                            value.write_text(out, indent + 1);
+                        }
+                        x => {
+                            let _ = writeln!(out, "{}- {}", pad, x.scalar());
+                        }
+                    }
+                }
+            }
+            x => {
+                let _ = writeln!(out, "{}{}", pad, x.scalar());
+            }
+        }
+    }
+
+    fn scalar(&self) -> String {
+        match self {
+            Node::Text(s) => s.clone(),
+            Node::Number(n) => n.to_string(),
+            Node::Bytes(b) if b.is_empty() => String::new(),
+            Node::Bytes(b) => format!("{} (base64 {})", bytes_to_hex_str(b), base64::encode(b)),
+            _ => String::new(),
+        }
+    }
+
+    /// Renders as compact JSON. Bytes become `{"hex": .., "base64": ..}`.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_value(&self) -> Value {
+        match self {
+            Node::Text(s) => Value::from(s.as_str()),
+            Node::Number(n) => Value::from(*n),
+            Node::Bytes(b) => json!({
+                "hex": bytes_to_hex_str(b),
+                "base64": base64::encode(b),
+            }),
+            Node::List(list) => Value::Array(list.iter().map(|x| x.to_value()).collect()),
+            Node::Fields(list) => Value::Object(
+                list.iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Decodes the header of `tx`. Parts failing to decode are shown as an
+/// `error` field with the raw bytes.
+pub fn inspect_transaction(tx: &Transaction) -> Node {
+    transaction_node(tx, false)
+}
+
+/// Like `inspect_transaction`, and decodes the payload as a `TPRequest` too.
+/// Most byte strings decode as some `TPRequest`, so use it only for families
+/// whose payloads are.
+pub fn inspect_transaction_with_request(tx: &Transaction) -> Node {
+    transaction_node(tx, true)
+}
+
+pub fn inspect_batch(batch: &Batch) -> Node {
+    batch_node(batch, false)
+}
+
+pub fn inspect_batch_with_request(batch: &Batch) -> Node {
+    batch_node(batch, true)
+}
+
+pub fn inspect_batch_list(list: &BatchList) -> Node {
+    batch_list_node(list, false)
+}
+
+pub fn inspect_batch_list_with_request(list: &BatchList) -> Node {
+    batch_list_node(list, true)
+}
+
+fn transaction_node(tx: &Transaction, request: bool) -> Node {
+    let header = match transaction_header(tx) {
+        Ok(h) => fields(vec![
+            ("batcher_public_key", text(&h.batcher_public_key)),
+            ("dependencies", texts(&h.dependencies)),
+            ("family_name", text(&h.family_name)),
+            ("family_version", text(&h.family_version)),
+            ("inputs", texts(&h.inputs)),
+            ("nonce", text(&h.nonce)),
+            ("outputs", texts(&h.outputs)),
+            ("payload_sha512", text(&h.payload_sha512)),
+            ("signer_public_key", text(&h.signer_public_key)),
+        ]),
+        Err(e) => fields(vec![
+            ("error", Node::Text(e.to_string())),
+            ("raw", Node::Bytes(tx.header.clone())),
+        ]),
+    };
+
+    let mut list = vec![
+        ("header_signature", text(&tx.header_signature)),
+        ("header", header),
+        ("payload", Node::Bytes(tx.payload.clone())),
+    ];
+
+    if request {
+        let req = match tp_request(tx) {
+            Ok(req) => fields(vec![
+                ("cmd", Node::Number(i64::from(req.cmd))),
+                ("payload", Node::Bytes(req.payload)),
+            ]),
+            Err(e) => fields(vec![("error", Node::Text(e.to_string()))]),
+        };
+        list.push(("request", req));
+    }
+
+    fields(list)
+}
+
+fn batch_node(batch: &Batch, request: bool) -> Node {
+    let header = match batch_header(batch) {
+        Ok(h) => fields(vec![
+            ("signer_public_key", text(&h.signer_public_key)),
+            ("transaction_ids", texts(&h.transaction_ids)),
+        ]),
+        Err(e) => fields(vec![
+            ("error", Node::Text(e.to_string())),
+            ("raw", Node::Bytes(batch.header.clone())),
+        ]),
+    };
+
+    fields(vec![
+        ("header_signature", text(&batch.header_signature)),
+        ("header", header),
+        (
+            "transactions",
+            Node::List(
+                batch
+                    .transactions
+                    .iter()
+                    .map(|x| transaction_node(x, request))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn batch_list_node(list: &BatchList, request: bool) -> Node {
+    fields(vec![(
+        "batches",
+        Node::List(
+            list.batches
+                .iter()
+                .map(|x| batch_node(x, request))
+                .collect(),
+        ),
+    )])
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::signer;
+    use crate::tx::{Batcher, TransactionBuilder};
+
+    #[test]
+    fn test_inspect() {
+        let signer = signer();
+        let req = TPRequest {
+            cmd: 3,
+            payload: vec![0xab, 0xcd],
+            ..TPRequest::default()
+        };
+        let tx = TransactionBuilder::new(&signer)
+            .family("intkey", "1.0")
+            .payload(&req)
+            .input("1cf126")
+            .nonce("n\"1")
+            .build()
+            .unwrap();
+        let batch = Batcher::new(&signer).build(&[tx]).unwrap();
+
+        let text = inspect_batch(&batch).to_text();
+        assert!(text.contains("  family_name: intkey\n"));
+        assert!(text.contains("  - 1cf126\n"));
+        assert!(!text.contains("cmd: 3\n"));
+
+        let text = inspect_batch_with_request(&batch).to_text();
+        assert!(text.contains("cmd: 3\n"));
+        assert!(text.contains("payload: abcd (base64 q80=)\n"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_inspect_json() {
+        let signer = signer();
+        let req = TPRequest {
+            cmd: 3,
+            payload: vec![0xab, 0xcd],
+            ..TPRequest::default()
+        };
+        let tx = TransactionBuilder::new(&signer)
+            .family("intkey", "1.0")
+            .payload(&req)
+            .input("1cf126")
+            .nonce("n\"1\u{1}\u{1f600}")
+            .build()
+            .unwrap();
+        let batch = Batcher::new(&signer).build(&[tx]).unwrap();
+
+        let json = inspect_batch_list_with_request(&Batcher::to_list(&[batch])).to_json();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        let tx = &value["batches"][0]["transactions"][0];
+        assert_eq!("n\"1\u{1}\u{1f600}", tx["header"]["nonce"]);
+        assert_eq!(
+            json!({"cmd": 3, "payload": {"hex": "abcd", "base64": "q80="}}),
+            tx["request"]
+        );
+
+        let bad = Transaction {
+            header: vec![0xff],
+            payload: vec![0xff],
+            ..Transaction::default()
+        };
+        let value = inspect_transaction(&bad).to_value();
+        assert!(value["header"]["error"].is_string());
+        assert!(value.get("request").is_none());
+
+        let value = inspect_transaction_with_request(&bad).to_value();
+        assert!(value["request"]["error"].is_string());
+    }
+}
@@ -23,12 +23,36 @@
  *
 */
 
-use super::signing::Signer;
-use super::{Error, Result};
-use crate::util::{nonce, sha512_bytes};
+use super::signing::Sign;
+use super::{parse, Error, Result};
+use crate::namespace::{is_valid_address, Namespace};
+use crate::util::{is_hex_str, is_public_key, sha512_bytes};
 use protobuf::{self, Message, RepeatedField};
 use sawtooth_sdk::messages::batch::{Batch, BatchHeader, BatchList};
 use sawtooth_sdk::messages::transaction::{Transaction, TransactionHeader};
+use sawtooth_sdk::messages::transaction_receipt::TransactionReceipt;
+
+mod inspect;
+mod nonce;
+mod session;
+mod split;
+mod template;
+mod verify;
+
+pub use self::inspect::{
+    batch_header, inspect_batch, inspect_batch_list, inspect_batch_list_with_request,
+    inspect_batch_with_request, inspect_transaction, inspect_transaction_with_request, tp_request,
+    transaction_header, Node,
+};
+pub use self::nonce::{
+    CounterNonce, FixedNonce, HashNonce, NonceSource, RandomNonce, TimestampNonce,
+};
+pub use self::session::Session;
+pub use self::split::{AutoBatcher, Limits};
+pub use self::template::sign_templates;
+pub use self::verify::{
+    verify_batch, verify_batch_list, verify_for_batcher, verify_transaction, Report, Violation,
+};
 
 // ----------------------------------------------------------------------------
 
@@ -64,6 +88,22 @@ impl Payload {
         batcher_public_key: &str,
         signer_public_key: &str,
         dependencies: &[String],
+    ) -> TransactionHeader {
+        self.tx_header_with(
+            batcher_public_key,
+            signer_public_key,
+            dependencies,
+            &RandomNonce,
+        )
+    }
+
+    /// Like `tx_header`, but takes the nonce from `source`.
+    pub fn tx_header_with(
+        &self,
+        batcher_public_key: &str,
+        signer_public_key: &str,
+        dependencies: &[String],
+        source: &dyn NonceSource,
     ) -> TransactionHeader {
         TransactionHeader {
             batcher_public_key: String::from(batcher_public_key),
@@ -71,7 +111,7 @@ impl Payload {
             family_name: self.family_name.clone(),
             family_version: self.family_version.clone(),
             inputs: RepeatedField::from_slice(&self.inputs),
-            nonce: nonce(),
+            nonce: source.nonce(&self.payload, signer_public_key),
             outputs: RepeatedField::from_slice(&self.outputs),
             payload_sha512: sha512_bytes(&self.payload),
             signer_public_key: String::from(signer_public_key),
@@ -83,12 +123,17 @@ impl Payload {
 // ----------------------------------------------------------------------------
 
 pub struct Builder<'a> {
-    signer: &'a Signer,
+    signer: &'a dyn Sign,
+    nonce: &'a dyn NonceSource,
 }
 
 impl<'a> Builder<'a> {
-    pub fn new(signer: &'a Signer) -> Self {
-        Builder { signer: signer }
+    pub fn new(signer: &'a dyn Sign) -> Self {
+        Builder::with_nonce(signer, &RandomNonce)
+    }
+
+    pub fn with_nonce(signer: &'a dyn Sign, nonce: &'a dyn NonceSource) -> Self {
+        Builder { signer, nonce }
     }
 
     pub fn header(
@@ -99,7 +144,12 @@ impl<'a> Builder<'a> {
     ) -> Result<TransactionHeader> {
         let signer_public_key = self.signer.get_public_key()?;
 
-        Ok(data.tx_header(batcher_public_key, &signer_public_key, dependencies))
+        Ok(data.tx_header_with(
+            batcher_public_key,
+            &signer_public_key,
+            dependencies,
+            self.nonce,
+        ))
     }
 
     pub fn build(
@@ -128,12 +178,221 @@ impl<'a> Builder<'a> {
 
 // ----------------------------------------------------------------------------
 
+/// Fluent builder of one transaction.
+///
+/// ```ignore
+/// let tx = TransactionBuilder::new(&signer)
+///     .family("intkey", "1.0")
+///     .payload(&req)
+///     .namespace(ns.as_ref())
+///     .build()?;
+/// ```
+///
+/// The batcher key defaults to the key of the signer. Addresses and ids are
+/// validated by `build`.
+pub struct TransactionBuilder<'a> {
+    signer: &'a dyn Sign,
+    family_name: String,
+    family_version: String,
+    message: Option<&'a dyn Message>,
+    payload: Option<Vec<u8>>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    dependencies: Vec<String>,
+    nonce: Option<String>,
+    nonce_source: &'a dyn NonceSource,
+    batcher_public_key: Option<String>,
+}
+
+impl<'a> TransactionBuilder<'a> {
+    pub fn new(signer: &'a dyn Sign) -> Self {
+        TransactionBuilder {
+            signer,
+            family_name: String::new(),
+            family_version: String::new(),
+            message: None,
+            payload: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            dependencies: Vec::new(),
+            nonce: None,
+            nonce_source: &RandomNonce,
+            batcher_public_key: None,
+        }
+    }
+
+    pub fn family(mut self, name: &str, version: &str) -> Self {
+        self.family_name = name.to_string();
+        self.family_version = version.to_string();
+        self
+    }
+
+    /// Sets the message encoded as the payload.
+    pub fn payload(mut self, msg: &'a dyn Message) -> Self {
+        self.message = Some(msg);
+        self.payload = None;
+        self
+    }
+
+    pub fn payload_bytes(mut self, payload: Vec<u8>) -> Self {
+        self.message = None;
+        self.payload = Some(payload);
+        self
+    }
+
+    /// Adds a namespace prefix or an address to the inputs.
+    pub fn input(mut self, address: &str) -> Self {
+        self.inputs.push(address.to_string());
+        self
+    }
+
+    pub fn inputs(mut self, addresses: &[String]) -> Self {
+        self.inputs.extend_from_slice(addresses);
+        self
+    }
+
+    /// Adds a namespace prefix or an address to the outputs.
+    pub fn output(mut self, address: &str) -> Self {
+        self.outputs.push(address.to_string());
+        self
+    }
+
+    pub fn outputs(mut self, addresses: &[String]) -> Self {
+        self.outputs.extend_from_slice(addresses);
+        self
+    }
+
+    /// Adds the prefix of `ns` to both the inputs and the outputs.
+    pub fn namespace(self, ns: &dyn Namespace) -> Self {
+        self.input(ns.prefix()).output(ns.prefix())
+    }
+
+    /// Adds the id of a transaction which must be committed before this one.
+    pub fn dependency(mut self, id: &str) -> Self {
+        self.dependencies.push(id.to_string());
+        self
+    }
+
+    pub fn dependencies(mut self, ids: &[String]) -> Self {
+        self.dependencies.extend_from_slice(ids);
+        self
+    }
+
+    /// Sets the nonce instead of generating a random one.
+    pub fn nonce(mut self, nonce: &str) -> Self {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    /// Generates the nonce with `source` unless set by `nonce`.
+    pub fn nonce_source(mut self, source: &'a dyn NonceSource) -> Self {
+        self.nonce_source = source;
+        self
+    }
+
+    pub fn batcher(mut self, public_key: &str) -> Self {
+        self.batcher_public_key = Some(public_key.to_string());
+        self
+    }
+
+    fn encoded_payload(&self) -> Result<Vec<u8>> {
+        match (self.message, &self.payload) {
+            (Some(msg), _) => msg.write_to_bytes().map_err(Error::Protobuf),
+            (None, Some(payload)) => Ok(payload.clone()),
+            (None, None) => Err(Error::MissingField("payload")),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.family_name.is_empty() {
+            return Err(Error::MissingField("family name"));
+        }
+
+        if self.family_version.is_empty() {
+            return Err(Error::MissingField("family version"));
+        }
+
+        for address in self.inputs.iter().chain(self.outputs.iter()) {
+            if !is_valid_address(address) {
+                return Err(Error::InvalidAddress(address.clone()));
+            }
+        }
+
+        for id in self.dependencies.iter() {
+            if id.len() != 128 || !is_hex_str(id) {
+                return Err(Error::InvalidTransactionId(id.clone()));
+            }
+        }
+
+        if let Some(key) = &self.batcher_public_key {
+            if !is_public_key(key) {
+                return Err(Error::InvalidPublicKey(key.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the settings and returns the header of the transaction.
+    pub fn header(&self) -> Result<TransactionHeader> {
+        self.header_for(&self.encoded_payload()?)
+    }
+
+    fn header_for(&self, payload: &[u8]) -> Result<TransactionHeader> {
+        self.validate()?;
+
+        let signer_public_key = self.signer.get_public_key()?;
+        let batcher_public_key = match &self.batcher_public_key {
+            Some(key) => key.clone(),
+            None => signer_public_key.clone(),
+        };
+
+        Ok(TransactionHeader {
+            batcher_public_key,
+            dependencies: RepeatedField::from_slice(&self.dependencies),
+            family_name: self.family_name.clone(),
+            family_version: self.family_version.clone(),
+            inputs: RepeatedField::from_slice(&self.inputs),
+            nonce: match &self.nonce {
+                Some(nonce) => nonce.clone(),
+                None => self.nonce_source.nonce(payload, &signer_public_key),
+            },
+            outputs: RepeatedField::from_slice(&self.outputs),
+            payload_sha512: sha512_bytes(payload),
+            signer_public_key,
+            ..TransactionHeader::default()
+        })
+    }
+
+    pub fn build(&self) -> Result<Transaction> {
+        let payload = self.encoded_payload()?;
+        let header = self.header_for(&payload)?;
+        let header_bytes = header.write_to_bytes().map_err(Error::Protobuf)?;
+        let header_signature = self.signer.sign(&header_bytes)?;
+
+        Ok(Transaction {
+            header: header_bytes,
+            header_signature,
+            payload,
+            ..Transaction::default()
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Signs batches.
+///
+/// When transactions are signed by many clients and batched by a separate
+/// service, clients declare the key of the service with
+/// `TransactionBuilder::batcher`, and the service accepts them with `check`
+/// or `build_checked`.
 pub struct Batcher<'a> {
-    signer: &'a Signer,
+    signer: &'a dyn Sign,
 }
 
 impl<'a> Batcher<'a> {
-    pub fn new(signer: &'a Signer) -> Self {
+    pub fn new(signer: &'a dyn Sign) -> Self {
         Batcher { signer: signer }
     }
 
@@ -173,6 +432,32 @@ impl<'a> Batcher<'a> {
         }
     }
 
+    /// Checks the signature and the payload hash of `tx`, and that it is
+    /// declared to be batched by this batcher.
+    pub fn check(&self, tx: &Transaction) -> Result<()> {
+        let report = verify_for_batcher(tx, &self.get_public_key()?);
+        if report.is_ok() {
+            Ok(())
+        } else {
+            Err(Error::Rejected(report))
+        }
+    }
+
+    /// Like `build`, but checks all `transactions` first, and rejects them
+    /// with the violations of all.
+    pub fn build_checked(&self, transactions: &[Transaction]) -> Result<Batch> {
+        let public_key = self.get_public_key()?;
+        let mut report = Report::default();
+        for tx in transactions {
+            report.extend(verify_for_batcher(tx, &public_key));
+        }
+
+        if !report.is_ok() {
+            return Err(Error::Rejected(report));
+        }
+        self.build(transactions)
+    }
+
     pub fn to_list(batches: &[Batch]) -> BatchList {
         BatchList {
             batches: RepeatedField::from_slice(batches),
@@ -180,3 +465,185 @@ impl<'a> Batcher<'a> {
         }
     }
 }
+
+// ----------------------------------------------------------------------------
+
+/// Decodes all data added by `tp::add_receipt_data` to `receipt`, in order.
+pub fn receipt_data<T: protobuf::Message>(receipt: &TransactionReceipt) -> Result<Vec<T>> {
+    receipt.data.iter().map(|bytes| parse::<T>(bytes)).collect()
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::signing::Signer;
+    use crate::test_util::{builder, signer, KEY_PUB_HEX};
+
+    #[test]
+    fn test_transaction_builder() {
+        let signer = signer();
+        let ns = namespace::new("intkey");
+        let address = ns.make_address("a1");
+        let req = TPRequest {
+            cmd: 1,
+            ..TPRequest::default()
+        };
+
+        let builder = TransactionBuilder::new(&signer)
+            .family("intkey", "1.0")
+            .payload(&req)
+            .namespace(ns.as_ref())
+            .output(&address)
+            .dependency(&"a".repeat(128))
+            .nonce("n1");
+
+        let tx = builder.build().unwrap();
+        let mut header = TransactionHeader::new();
+        header.merge_from_bytes(&tx.header).unwrap();
+        assert_eq!("intkey", header.family_name);
+        assert_eq!("1.0", header.family_version);
+        assert_eq!(vec!["1cf126"], header.inputs.to_vec());
+        assert_eq!(vec!["1cf126".to_string(), address], header.outputs.to_vec());
+        assert_eq!("n1", header.nonce);
+        assert_eq!(KEY_PUB_HEX, header.batcher_public_key);
+        assert_eq!(KEY_PUB_HEX, header.signer_public_key);
+        assert_eq!(sha512_bytes(&tx.payload), header.payload_sha512);
+        assert_eq!(req.write_to_bytes().unwrap(), tx.payload);
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let signer = signer();
+        let build =
+            |source: &dyn NonceSource| builder(&signer).nonce_source(source).build().unwrap();
+
+        assert_eq!(build(&HashNonce), build(&HashNonce));
+        assert_ne!(build(&RandomNonce), build(&RandomNonce));
+
+        let counter = CounterNonce::new(1);
+        let tx1 = build(&counter);
+        let tx2 = build(&CounterNonce::new(1));
+        assert_eq!(tx1.header_signature, tx2.header_signature);
+
+        let payload = Payload::new(
+            "intkey".to_string(),
+            "1.0".to_string(),
+            &TPRequest::default(),
+            &["1cf126".to_string()],
+            &["1cf126".to_string()],
+        )
+        .unwrap();
+        let fixed = FixedNonce("n1".to_string());
+        let builder = Builder::with_nonce(&signer, &fixed);
+        let tx1 = builder.build(KEY_PUB_HEX, &payload, &[]).unwrap();
+        let tx2 = builder.build(KEY_PUB_HEX, &payload, &[]).unwrap();
+        assert_eq!(tx1, tx2);
+    }
+
+    #[test]
+    fn test_transaction_builder_validate() {
+        let signer = signer();
+        let builder = || {
+            TransactionBuilder::new(&signer)
+                .family("intkey", "1.0")
+                .payload_bytes(vec![1])
+        };
+
+        assert!(builder().input("1cf126").build().is_ok());
+
+        match TransactionBuilder::new(&signer)
+            .payload_bytes(vec![])
+            .build()
+        {
+            Err(Error::MissingField(_)) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        match TransactionBuilder::new(&signer)
+            .family("intkey", "1.0")
+            .build()
+        {
+            Err(Error::MissingField("payload")) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        match builder().input("1cf12").build() {
+            Err(Error::InvalidAddress(x)) => assert_eq!("1cf12", x),
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        match builder().output(&"z".repeat(70)).build() {
+            Err(Error::InvalidAddress(_)) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        match builder().dependency("abc").build() {
+            Err(Error::InvalidTransactionId(_)) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+        match builder().batcher("02ab").build() {
+            Err(Error::InvalidPublicKey(_)) => {}
+            x => panic!("unexpected {:?}", x.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_receipt_data() {
+        let mut receipt = TransactionReceipt::default();
+        for cmd in 1..3 {
+            let msg = TPRequest {
+                cmd,
+                ..TPRequest::default()
+            };
+            receipt.data.push(msg.write_to_bytes().unwrap());
+        }
+
+        let data = receipt_data::<TPRequest>(&receipt).unwrap();
+        assert_eq!(vec![1, 2], data.iter().map(|x| x.cmd).collect::<Vec<_>>());
+
+        receipt.data.push(vec![0xff]);
+        assert!(receipt_data::<TPRequest>(&receipt).is_err());
+    }
+
+    #[test]
+    fn test_multi_signer() {
+        let client = signer();
+        let service =
+            Signer::from_hex("51b845c2cdde22fe646148f0b51eaf5feec8c82ee921d5e0cbe7619f3bb9c62d")
+                .unwrap();
+        let service_key = service.get_public_key().unwrap();
+        let build = |batcher: &str| builder(&client).batcher(batcher).build().unwrap();
+
+        let batcher = Batcher::new(&service);
+        let tx1 = build(&service_key);
+        batcher.check(&tx1).unwrap();
+        let batch = batcher.build_checked(std::slice::from_ref(&tx1)).unwrap();
+        assert!(verify_batch(&batch).is_ok());
+
+        // signed for the client itself, and tampered.
+        let tx2 = build(KEY_PUB_HEX);
+        let mut tx3 = build(&service_key);
+        tx3.payload = vec![4];
+        match batcher.build_checked(&[tx1.clone(), tx2.clone(), tx3]) {
+            Err(Error::Rejected(report)) => {
+                assert_eq!(2, report.violations.len());
+                match &report.violations[0] {
+                    Violation::BatcherMismatch {
+                        id, batch_signer, ..
+                    } => {
+                        assert_eq!(&tx2.header_signature, id);
+                        assert_eq!(&service_key, batch_signer);
+                    }
+                    x => panic!("unexpected {}", x),
+                }
+            }
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let mut auto = AutoBatcher::new(&service, Limits::default());
+        auto.accept(tx1).unwrap();
+        assert!(auto.accept(tx2).is_err());
+        let lists = auto.finish().unwrap();
+        assert_eq!(1, lists[0].batches[0].transactions.len());
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Sources of transaction nonces.
+//!
+//! The nonce is the only part of a transaction header not fixed by its
+//! content. Secp256k1 signatures are deterministic, so building a transaction
+//! twice with the same nonce yields the same `header_signature`.
+
+use crate::util::{self, sha512_raw};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ----------------------------------------------------------------------------
+
+pub trait NonceSource {
+    /// Returns the nonce of the transaction of `payload` signed by
+    /// `signer_public_key`.
+    fn nonce(&self, payload: &[u8], signer_public_key: &str) -> String;
+}
+
+impl<F> NonceSource for F
+where
+    F: Fn(&[u8], &str) -> String,
+{
+    fn nonce(&self, payload: &[u8], signer_public_key: &str) -> String {
+        self(payload, signer_public_key)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Random UUIDv4 nonces, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomNonce;
+
+impl NonceSource for RandomNonce {
+    fn nonce(&self, _: &[u8], _: &str) -> String {
+        util::nonce()
+    }
+}
+
+/// The same caller supplied nonce for every transaction.
+#[derive(Debug, Clone)]
+pub struct FixedNonce(pub String);
+
+impl NonceSource for FixedNonce {
+    fn nonce(&self, _: &[u8], _: &str) -> String {
+        self.0.clone()
+    }
+}
+
+/// `{prefix}{n}` with `n` counting up from a start value.
+#[derive(Debug)]
+pub struct CounterNonce {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl CounterNonce {
+    pub fn new(start: u64) -> Self {
+        CounterNonce::with_prefix("", start)
+    }
+
+    pub fn with_prefix(prefix: &str, start: u64) -> Self {
+        CounterNonce {
+            prefix: prefix.to_string(),
+            next: AtomicU64::new(start),
+        }
+    }
+}
+
+impl NonceSource for CounterNonce {
+    fn nonce(&self, _: &[u8], _: &str) -> String {
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        format!("{}{}", self.prefix, n)
+    }
+}
+
+/// `{milliseconds since epoch}-{n}`, unique within a process even when
+/// several transactions are built in the same millisecond.
+#[derive(Debug, Default)]
+pub struct TimestampNonce {
+    next: AtomicU64,
+}
+
+impl TimestampNonce {
+    pub fn new() -> Self {
+        TimestampNonce::default()
+    }
+}
+
+impl NonceSource for TimestampNonce {
+    fn nonce(&self, _: &[u8], _: &str) -> String {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_millis())
+            .unwrap_or_default();
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        format!("{}-{}", millis, n)
+    }
+}
+
+/// Nonce derived from the signer and the payload, so resubmitting the same
+/// payload by the same signer yields the same transaction id. Two identical
+/// payloads of one signer are thus the same transaction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashNonce;
+
+impl NonceSource for HashNonce {
+    fn nonce(&self, payload: &[u8], signer_public_key: &str) -> String {
+        let mut input = Vec::from(signer_public_key.as_bytes());
+        input.extend_from_slice(payload);
+        util::bytes_to_hex_str(&sha512_raw(&input)[..32])
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonce_source() {
+        assert_ne!(RandomNonce.nonce(b"p", "k"), RandomNonce.nonce(b"p", "k"));
+        assert_eq!("n", FixedNonce("n".to_string()).nonce(b"p", "k"));
+
+        let counter = CounterNonce::with_prefix("c-", 7);
+        assert_eq!("c-7", counter.nonce(b"p", "k"));
+        assert_eq!("c-8", counter.nonce(b"p", "k"));
+
+        let ts = TimestampNonce::new();
+        assert!(ts.nonce(b"p", "k").ends_with("-0"));
+        assert!(ts.nonce(b"p", "k").ends_with("-1"));
+
+        assert_eq!(HashNonce.nonce(b"p", "k"), HashNonce.nonce(b"p", "k"));
+        assert_ne!(HashNonce.nonce(b"p", "k"), HashNonce.nonce(b"q", "k"));
+        assert_ne!(HashNonce.nonce(b"p", "k"), HashNonce.nonce(b"p", "j"));
+        assert_eq!(64, HashNonce.nonce(b"p", "k").len());
+
+        let f = |payload: &[u8], _: &str| format!("{}", payload.len());
+        assert_eq!("3", f.nonce(b"abc", "k"));
+    }
+}
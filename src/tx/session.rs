@@ -0,0 +1,232 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Dependencies derived from the inputs and outputs of built transactions.
+
+use super::{transaction_header, Builder, Payload, TransactionBuilder};
+use crate::Result;
+use sawtooth_sdk::messages::transaction::Transaction;
+
+// ----------------------------------------------------------------------------
+
+/// Addresses overlap if one is a prefix of the other.
+fn overlaps(a: &str, b: &str) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+/// Remembers the transactions built through it, and makes every new one
+/// depend on the latest earlier ones writing to its inputs or outputs, so they
+/// are applied in order even when submitted in different batches. Older
+/// writers of an address are ordered before the latest one through its own
+/// dependencies, so they are left out to keep headers small.
+#[derive(Debug, Default)]
+pub struct Session {
+    history: Vec<(String, Vec<String>)>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Returns the ids of the latest earlier transactions writing to each
+    /// output overlapping `addresses`, in the order built.
+    pub fn dependencies(&self, addresses: &[String]) -> Vec<String> {
+        let mut written: Vec<&str> = Vec::new();
+        let mut ret = Vec::new();
+        for (id, outputs) in self.history.iter().rev() {
+            let mut latest = false;
+            for output in outputs {
+                if addresses.iter().any(|x| overlaps(x, output))
+                    && !written.iter().any(|x| output.starts_with(x))
+                {
+                    written.push(output);
+                    latest = true;
+                }
+            }
+            if latest {
+                ret.push(id.clone());
+            }
+        }
+        ret.reverse();
+        ret
+    }
+
+    /// Records `tx` built elsewhere.
+    pub fn record(&mut self, tx: &Transaction) -> Result<()> {
+        let header = transaction_header(tx)?;
+        self.history
+            .push((tx.header_signature.clone(), header.outputs.into_vec()));
+        Ok(())
+    }
+
+    pub fn build(&mut self, mut builder: TransactionBuilder) -> Result<Transaction> {
+        let addresses = [builder.inputs.as_slice(), builder.outputs.as_slice()].concat();
+        for id in self.dependencies(&addresses) {
+            if !builder.dependencies.contains(&id) {
+                builder.dependencies.push(id);
+            }
+        }
+
+        let tx = builder.build()?;
+        self.history
+            .push((tx.header_signature.clone(), builder.outputs));
+        Ok(tx)
+    }
+
+    /// Like `Builder::build`, with the derived dependencies added to
+    /// `dependencies`.
+    pub fn build_payload(
+        &mut self,
+        builder: &Builder,
+        batcher_public_key: &str,
+        data: &Payload,
+        dependencies: &[String],
+    ) -> Result<Transaction> {
+        let mut deps = Vec::from(dependencies);
+        let addresses = [data.inputs.as_slice(), data.outputs.as_slice()].concat();
+        for id in self.dependencies(&addresses) {
+            if !deps.contains(&id) {
+                deps.push(id);
+            }
+        }
+
+        let tx = builder.build(batcher_public_key, data, &deps)?;
+        self.history
+            .push((tx.header_signature.clone(), data.outputs.clone()));
+        Ok(tx)
+    }
+
+    /// Forgets all transactions, e.g. once they are committed.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::test_util::{signer, KEY_PUB_HEX};
+
+    fn dependencies(tx: &Transaction) -> Vec<String> {
+        transaction_header(tx).unwrap().dependencies.into_vec()
+    }
+
+    #[test]
+    fn test_session() {
+        let signer = signer();
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+        let builder = || {
+            TransactionBuilder::new(&signer)
+                .family("intkey", "1.0")
+                .payload_bytes(vec![1])
+        };
+
+        let mut session = Session::new();
+        let create1 = session.build(builder().input(&a1).output(&a1)).unwrap();
+        let create2 = session.build(builder().input(&a2).output(&a2)).unwrap();
+        assert!(dependencies(&create1).is_empty());
+        assert!(dependencies(&create2).is_empty());
+
+        let transfer = session
+            .build(builder().input(&a1).input(&a2).output(&a1).output(&a2))
+            .unwrap();
+        assert_eq!(
+            vec![
+                create1.header_signature.clone(),
+                create2.header_signature.clone()
+            ],
+            dependencies(&transfer)
+        );
+
+        // a namespace wide input overlaps every address in it.
+        let payload = Payload::new(
+            "intkey".to_string(),
+            "1.0".to_string(),
+            &TPRequest::default(),
+            &[ns.prefix().to_string()],
+            &[],
+        )
+        .unwrap();
+        let tx = session
+            .build_payload(
+                &Builder::new(&signer),
+                KEY_PUB_HEX,
+                &payload,
+                std::slice::from_ref(&create1.header_signature),
+            )
+            .unwrap();
+        // create1 is passed in, create2 is ordered through transfer.
+        assert_eq!(
+            vec![create1.header_signature, transfer.header_signature],
+            dependencies(&tx)
+        );
+
+        session.clear();
+        let mut other = Session::new();
+        other.record(&tx).unwrap();
+        assert!(other.dependencies(&[a1]).is_empty());
+    }
+
+    #[test]
+    fn test_latest_writer() {
+        let signer = signer();
+        let ns = namespace::new("intkey");
+        let a1 = ns.make_address("a1");
+        let a2 = ns.make_address("a2");
+        let builder = || {
+            TransactionBuilder::new(&signer)
+                .family("intkey", "1.0")
+                .payload_bytes(vec![1])
+        };
+
+        let mut session = Session::new();
+        let mut last = session.build(builder().input(&a1).output(&a1)).unwrap();
+        for _ in 0..10 {
+            let tx = session.build(builder().input(&a1).output(&a1)).unwrap();
+            assert_eq!(vec![last.header_signature], dependencies(&tx));
+            last = tx;
+        }
+
+        // a blind write is ordered after the earlier writer too.
+        let write = session.build(builder().output(&a1)).unwrap();
+        assert_eq!(vec![last.header_signature], dependencies(&write));
+
+        let other = session.build(builder().input(&a2).output(&a2)).unwrap();
+        let tx = session
+            .build(builder().input(&a1).input(&a2).output(&a1))
+            .unwrap();
+        assert_eq!(
+            vec![write.header_signature, other.header_signature],
+            dependencies(&tx)
+        );
+    }
+}
@@ -0,0 +1,396 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Batching a stream of transactions within the limits of the validator.
+
+use super::{transaction_header, Batcher};
+use crate::signing::Sign;
+use crate::{Error, Result};
+use protobuf::Message;
+use sawtooth_sdk::messages::batch::{Batch, BatchList};
+use sawtooth_sdk::messages::transaction::Transaction;
+use std::collections::HashSet;
+
+/// Bytes of a batch other than its transactions: the signature, the public
+/// key of the signer and the framing, with some spare.
+static BATCH_OVERHEAD: usize = 256;
+
+/// Bytes of the id of a transaction in a batch header.
+static ID_SIZE: usize = 128 + 3;
+
+// ----------------------------------------------------------------------------
+
+/// Limits of batches and batch lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_transactions: usize,
+    pub max_batch_bytes: usize,
+    pub max_batches: usize,
+    pub max_list_bytes: usize,
+}
+
+impl Default for Limits {
+    /// 100 transactions or 1 MiB per batch, 100 batches or 10 MiB per list,
+    /// within the defaults of the validator and the REST API.
+    fn default() -> Self {
+        Limits {
+            max_transactions: 100,
+            max_batch_bytes: 1024 * 1024,
+            max_batches: 100,
+            max_list_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+pub(super) fn framed(size: usize) -> usize {
+    // tag and length prefix of an embedded message.
+    size + 1 + protobuf::rt::compute_raw_varint64_size(size as u64) as usize
+}
+
+// ----------------------------------------------------------------------------
+
+/// Groups pushed transactions into batches and batch lists within `Limits`.
+///
+/// Transactions are batched in the order pushed, except one depending on a
+/// transaction not pushed yet is held back until that one is batched, so
+/// dependencies always land in the same or an earlier batch. Dependencies
+/// never pushed are taken as committed already, and their dependents are
+/// batched by `finish`.
+pub struct AutoBatcher<'a> {
+    batcher: Batcher<'a>,
+    limits: Limits,
+    pending: Vec<Transaction>,
+    pending_bytes: usize,
+    held: Vec<(Transaction, Vec<String>)>,
+    batched: HashSet<String>,
+    batches: Vec<Batch>,
+    batches_bytes: usize,
+    lists: Vec<BatchList>,
+}
+
+impl<'a> AutoBatcher<'a> {
+    pub fn new(signer: &'a dyn Sign, limits: Limits) -> Self {
+        AutoBatcher {
+            batcher: Batcher::new(signer),
+            limits,
+            pending: Vec::new(),
+            pending_bytes: 0,
+            held: Vec::new(),
+            batched: HashSet::new(),
+            batches: Vec::new(),
+            batches_bytes: 0,
+            lists: Vec::new(),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Like `push`, but checks `tx` with `Batcher::check` first, e.g. when
+    /// collecting transactions signed by clients.
+    pub fn accept(&mut self, tx: Transaction) -> Result<()> {
+        self.batcher.check(&tx)?;
+        self.push(tx)
+    }
+
+    /// Pushes all transactions, stops at the first one failing.
+    pub fn push_all<I: IntoIterator<Item = Transaction>>(&mut self, iter: I) -> Result<()> {
+        for tx in iter {
+            self.push(tx)?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, tx: Transaction) -> Result<()> {
+        let size = framed(tx.compute_size() as usize) + ID_SIZE;
+        if size + BATCH_OVERHEAD > self.limits.max_batch_bytes {
+            return Err(Error::TooLarge(tx.header_signature, size));
+        }
+
+        let header = transaction_header(&tx)?;
+
+        let waiting: Vec<String> = header
+            .dependencies
+            .into_iter()
+            .filter(|x| !self.batched.contains(x))
+            .collect();
+
+        if waiting.is_empty() {
+            self.place(tx)
+        } else {
+            self.held.push((tx, waiting));
+            Ok(())
+        }
+    }
+
+    /// Places `tx` in the pending batch, then every held transaction it
+    /// releases.
+    fn place(&mut self, tx: Transaction) -> Result<()> {
+        let mut ready = vec![tx];
+        while let Some(tx) = ready.pop() {
+            self.append(tx)?;
+
+            let mut idx = 0;
+            while idx < self.held.len() {
+                let batched = &self.batched;
+                let waiting = &mut self.held[idx].1;
+                waiting.retain(|x| !batched.contains(x));
+                if waiting.is_empty() {
+                    ready.insert(0, self.held.remove(idx).0);
+                } else {
+                    idx += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, tx: Transaction) -> Result<()> {
+        let size = framed(tx.compute_size() as usize) + ID_SIZE;
+        if self.pending.len() >= self.limits.max_transactions
+            || self.pending_bytes + size + BATCH_OVERHEAD > self.limits.max_batch_bytes
+        {
+            self.seal()?;
+        }
+
+        self.batched.insert(tx.header_signature.clone());
+        self.pending_bytes += size;
+        self.pending.push(tx);
+        Ok(())
+    }
+
+    /// Builds the pending transactions into a batch.
+    fn seal(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let batch = self.batcher.build(&self.pending)?;
+        self.pending.clear();
+        self.pending_bytes = 0;
+
+        let size = framed(batch.compute_size() as usize);
+        if self.batches_bytes + size > self.limits.max_list_bytes {
+            self.seal_list();
+        }
+
+        self.batches_bytes += size;
+        self.batches.push(batch);
+        if self.batches.len() >= self.limits.max_batches {
+            self.seal_list();
+        }
+        Ok(())
+    }
+
+    fn seal_list(&mut self) {
+        if self.batches.is_empty() {
+            return;
+        }
+
+        self.lists.push(Batcher::to_list(&self.batches));
+        self.batches.clear();
+        self.batches_bytes = 0;
+    }
+
+    /// Takes the batch lists filled up so far.
+    pub fn take_lists(&mut self) -> Vec<BatchList> {
+        std::mem::take(&mut self.lists)
+    }
+
+    /// Batches everything left, including transactions held back for
+    /// dependencies never pushed, and returns the remaining batch lists.
+    pub fn finish(mut self) -> Result<Vec<BatchList>> {
+        let held_ids: HashSet<String> = self
+            .held
+            .iter()
+            .map(|x| x.0.header_signature.clone())
+            .collect();
+        for (_, waiting) in self.held.iter_mut() {
+            waiting.retain(|x| held_ids.contains(x));
+        }
+
+        while let Some(idx) = self.held.iter().position(|x| x.1.is_empty()) {
+            let tx = self.held.remove(idx).0;
+            self.place(tx)?;
+        }
+
+        // Only transactions depending on each other in a cycle are left.
+        let held: Vec<Transaction> = self.held.drain(..).map(|x| x.0).collect();
+        for tx in held {
+            self.append(tx)?;
+        }
+
+        self.seal()?;
+        self.seal_list();
+        Ok(self.lists)
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::Signer;
+    use crate::test_util::{builder, signer};
+    use crate::tx::verify_batch_list;
+
+    fn transaction(signer: &Signer, nonce: &str, size: usize, deps: &[String]) -> Transaction {
+        builder(signer)
+            .payload_bytes(vec![0; size])
+            .dependencies(deps)
+            .nonce(nonce)
+            .build()
+            .unwrap()
+    }
+
+    fn ids(list: &BatchList) -> Vec<Vec<String>> {
+        list.batches
+            .iter()
+            .map(|b| {
+                b.transactions
+                    .iter()
+                    .map(|x| x.header_signature.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_by_count() {
+        let signer = signer();
+        let limits = Limits {
+            max_transactions: 2,
+            max_batches: 2,
+            ..Limits::default()
+        };
+
+        let mut batcher = AutoBatcher::new(&signer, limits);
+        batcher
+            .push_all((0..5).map(|n| transaction(&signer, &n.to_string(), 1, &[])))
+            .unwrap();
+        let mut lists = batcher.take_lists();
+        assert_eq!(1, lists.len());
+        lists.extend(batcher.finish().unwrap());
+
+        assert_eq!(
+            vec![2, 1],
+            lists.iter().map(|x| x.batches.len()).collect::<Vec<_>>()
+        );
+        assert_eq!(1, lists[1].batches[0].transactions.len());
+        for list in lists.iter() {
+            assert!(verify_batch_list(list).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_split_by_size() {
+        let signer = signer();
+        let limits = Limits {
+            max_batch_bytes: 4096,
+            max_list_bytes: 8192,
+            ..Limits::default()
+        };
+
+        let mut batcher = AutoBatcher::new(&signer, limits);
+        for n in 0..10 {
+            batcher
+                .push(transaction(&signer, &n.to_string(), 1000, &[]))
+                .unwrap();
+        }
+        let lists = batcher.finish().unwrap();
+
+        let mut count = 0;
+        for list in lists.iter() {
+            assert!(list.compute_size() as usize <= limits.max_list_bytes);
+            for batch in list.batches.iter() {
+                assert!(batch.compute_size() as usize <= limits.max_batch_bytes);
+                count += batch.transactions.len();
+            }
+        }
+        assert_eq!(10, count);
+        assert!(lists.len() > 1);
+
+        let mut batcher = AutoBatcher::new(&signer, limits);
+        match batcher.push(transaction(&signer, "big", 5000, &[])) {
+            Err(Error::TooLarge(_, _)) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let signer = signer();
+        let limits = Limits {
+            max_transactions: 1,
+            ..Limits::default()
+        };
+
+        let a = transaction(&signer, "a", 1, &[]);
+        let external = "e".repeat(128);
+        let b = transaction(&signer, "b", 1, std::slice::from_ref(&a.header_signature));
+        let c = transaction(&signer, "c", 1, &[external]);
+
+        let mut batcher = AutoBatcher::new(&signer, limits);
+        batcher.push(b.clone()).unwrap();
+        batcher.push(c.clone()).unwrap();
+        batcher.push(a.clone()).unwrap();
+        let lists = batcher.finish().unwrap();
+
+        assert_eq!(
+            vec![
+                vec![a.header_signature],
+                vec![b.header_signature],
+                vec![c.header_signature]
+            ],
+            ids(&lists[0])
+        );
+    }
+
+    #[test]
+    fn test_chained_dependencies() {
+        let signer = signer();
+        let external = "e".repeat(128);
+        let x = transaction(&signer, "x", 1, &[external]);
+        let y = transaction(&signer, "y", 1, std::slice::from_ref(&x.header_signature));
+        let z = transaction(&signer, "z", 1, std::slice::from_ref(&y.header_signature));
+
+        let mut batcher = AutoBatcher::new(&signer, Limits::default());
+        batcher.push(z.clone()).unwrap();
+        batcher.push(y.clone()).unwrap();
+        batcher.push(x.clone()).unwrap();
+        let lists = batcher.finish().unwrap();
+
+        assert_eq!(
+            vec![vec![
+                x.header_signature,
+                y.header_signature,
+                z.header_signature
+            ]],
+            ids(&lists[0])
+        );
+    }
+}
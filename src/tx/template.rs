@@ -0,0 +1,262 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Unsigned transaction templates for signing on an offline host.
+//!
+//! Templates are prepared online as a `TransactionTemplates` file, signed
+//! offline by `sign_templates` and brought back as a `BatchList` file.
+
+use super::split::framed;
+use super::{Batcher, Limits, Payload, TransactionBuilder};
+use crate::messages::template::{TransactionTemplate, TransactionTemplates};
+use crate::signing::Sign;
+use crate::{Error, Result};
+use protobuf::{Message, RepeatedField};
+use sawtooth_sdk::messages::batch::BatchList;
+
+// ----------------------------------------------------------------------------
+
+impl Payload {
+    /// Returns the template of a transaction carrying this payload.
+    pub fn template(&self) -> TransactionTemplate {
+        TransactionTemplate {
+            family_name: self.family_name.clone(),
+            family_version: self.family_version.clone(),
+            inputs: RepeatedField::from_slice(&self.inputs),
+            outputs: RepeatedField::from_slice(&self.outputs),
+            payload: self.payload.clone(),
+            ..TransactionTemplate::default()
+        }
+    }
+}
+
+impl TransactionTemplates {
+    pub fn push(&mut self, template: TransactionTemplate) -> usize {
+        self.transactions.push(template);
+        self.transactions.len() - 1
+    }
+}
+
+/// Signs all `templates` into one batch, so they are committed together and
+/// in order. `after` indices are resolved to the ids of the earlier
+/// transactions, and an empty nonce is generated. Fails with `TooLarge` when
+/// the batch exceeds `limits`, since splitting it would lose atomicity. The
+/// count and payload sizes are checked before signing anything.
+pub fn sign_templates(
+    signer: &dyn Sign,
+    templates: &TransactionTemplates,
+    limits: &Limits,
+) -> Result<BatchList> {
+    // the payloads alone are a lower bound of the batch, checked before
+    // signing anything.
+    let count = templates.transactions.len();
+    let payload_bytes: usize = templates
+        .transactions
+        .iter()
+        .map(|x| framed(x.payload.len()))
+        .sum();
+    if count > limits.max_transactions
+        || payload_bytes > limits.max_batch_bytes
+        || payload_bytes > limits.max_list_bytes
+    {
+        return Err(Error::TooLarge(
+            format!("batch of {} transactions", count),
+            payload_bytes,
+        ));
+    }
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut transactions = Vec::new();
+
+    for (idx, template) in templates.transactions.iter().enumerate() {
+        let mut builder = TransactionBuilder::new(signer)
+            .family(&template.family_name, &template.family_version)
+            .payload_bytes(template.payload.clone())
+            .inputs(&template.inputs)
+            .outputs(&template.outputs)
+            .dependencies(&template.dependencies);
+
+        for after in template.after.iter() {
+            let id = match ids.get(*after as usize) {
+                Some(id) if (*after as usize) < idx => id,
+                _ => return Err(Error::InvalidTemplate(format!("#{} after #{}", idx, after))),
+            };
+            builder = builder.dependency(id);
+        }
+
+        if !template.nonce.is_empty() {
+            builder = builder.nonce(&template.nonce);
+        }
+
+        let tx = builder.build()?;
+        ids.push(tx.header_signature.clone());
+        transactions.push(tx);
+    }
+
+    let batch = Batcher::new(signer).build(&transactions)?;
+    let size = framed(batch.compute_size() as usize);
+    if transactions.len() > limits.max_transactions
+        || size > limits.max_batch_bytes
+        || size > limits.max_list_bytes
+    {
+        return Err(Error::TooLarge(
+            format!("batch of {} transactions", transactions.len()),
+            size,
+        ));
+    }
+
+    Ok(Batcher::to_list(std::slice::from_ref(&batch)))
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::request::TPRequest;
+    use crate::namespace;
+    use crate::test_util::signer;
+    use crate::tx::{transaction_header, verify_batch_list};
+    use protobuf::Message;
+    use std::cell::Cell;
+
+    /// Counts the signatures made by `signer`.
+    struct Counting<'a> {
+        signer: &'a dyn Sign,
+        count: Cell<usize>,
+    }
+
+    impl<'a> Sign for Counting<'a> {
+        fn get_public_key(&self) -> Result<String> {
+            self.signer.get_public_key()
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<String> {
+            self.count.set(self.count.get() + 1);
+            self.signer.sign(message)
+        }
+    }
+
+    #[test]
+    fn test_sign_templates() {
+        let ns = namespace::new("intkey");
+        let prefix = vec![ns.prefix().to_string()];
+        let req = TPRequest {
+            cmd: 1,
+            ..TPRequest::default()
+        };
+        let payload = Payload::new(
+            "intkey".to_string(),
+            "1.0".to_string(),
+            &req,
+            &prefix,
+            &prefix,
+        )
+        .unwrap();
+
+        let mut templates = TransactionTemplates::default();
+        let first = templates.push(payload.template());
+        let mut second = payload.template();
+        second.nonce = "n2".to_string();
+        second.after.push(first as u32);
+        templates.push(second);
+
+        // round trip through the file format.
+        let bytes = templates.write_to_bytes().unwrap();
+        let mut templates = TransactionTemplates::default();
+        templates.merge_from_bytes(&bytes).unwrap();
+
+        let signer = signer();
+        let list = sign_templates(&signer, &templates, &Limits::default()).unwrap();
+        assert!(verify_batch_list(&list).is_ok());
+
+        let txs = &list.batches[0].transactions;
+        assert_eq!(2, txs.len());
+        let header = transaction_header(&txs[1]).unwrap();
+        assert_eq!("n2", header.nonce);
+        assert_eq!(
+            vec![txs[0].header_signature.clone()],
+            header.dependencies.to_vec()
+        );
+        assert_eq!(req.write_to_bytes().unwrap(), txs[0].payload);
+
+        templates.transactions[0].after.push(1);
+        match sign_templates(&signer, &templates, &Limits::default()) {
+            Err(Error::InvalidTemplate(x)) => assert_eq!("#0 after #1", x),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        templates.transactions[0].after.clear();
+        templates.transactions[0].inputs.push("xyz".to_string());
+        match sign_templates(&signer, &templates, &Limits::default()) {
+            Err(Error::InvalidAddress(_)) => {}
+            x => panic!("unexpected {:?}", x),
+        }
+
+        templates.transactions[0].inputs.clear();
+        let limits = Limits {
+            max_transactions: 1,
+            ..Limits::default()
+        };
+        match sign_templates(&signer, &templates, &limits) {
+            Err(Error::TooLarge(x, _)) => assert_eq!("batch of 2 transactions", x),
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+    #[test]
+    fn test_limits_before_signing() {
+        let signer = signer();
+        let counting = Counting {
+            signer: &signer,
+            count: Cell::new(0),
+        };
+
+        let mut templates = TransactionTemplates::default();
+        for _ in 0..3 {
+            templates.push(TransactionTemplate {
+                family_name: "intkey".to_string(),
+                family_version: "1.0".to_string(),
+                payload: vec![0; 1000],
+                ..TransactionTemplate::default()
+            });
+        }
+
+        let limits = Limits {
+            max_batch_bytes: 2048,
+            ..Limits::default()
+        };
+        match sign_templates(&counting, &templates, &limits) {
+            Err(Error::TooLarge(x, _)) => assert_eq!("batch of 3 transactions", x),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let limits = Limits {
+            max_transactions: 2,
+            ..Limits::default()
+        };
+        assert!(sign_templates(&counting, &templates, &limits).is_err());
+        assert_eq!(0, counting.count.get());
+    }
+}
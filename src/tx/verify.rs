@@ -0,0 +1,393 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Verification of signed transactions and batches.
+
+use crate::signing::Verifier;
+use crate::util::sha512_bytes;
+use crate::Error;
+use sawtooth_sdk::messages::batch::{Batch, BatchHeader, BatchList};
+use sawtooth_sdk::messages::transaction::{Transaction, TransactionHeader};
+use std::collections::HashMap;
+use std::fmt;
+
+// ----------------------------------------------------------------------------
+
+/// One failed check. `id` is the header signature of the transaction or
+/// batch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    MalformedHeader {
+        id: String,
+        error: String,
+    },
+    InvalidPublicKey {
+        id: String,
+        public_key: String,
+    },
+    InvalidSignature {
+        id: String,
+    },
+    /// The signature could not be checked, e.g. it is not valid hex.
+    MalformedSignature {
+        id: String,
+        error: String,
+    },
+    PayloadHashMismatch {
+        id: String,
+    },
+    BatcherMismatch {
+        id: String,
+        batcher_public_key: String,
+        batch_signer: String,
+    },
+    TransactionIdsMismatch {
+        id: String,
+        header: Vec<String>,
+        transactions: Vec<String>,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::MalformedHeader { id, error } => {
+                write!(f, "{}: malformed header {}", id, error)
+            }
+            Violation::InvalidPublicKey { id, public_key } => {
+                write!(f, "{}: invalid public key {}", id, public_key)
+            }
+            Violation::InvalidSignature { id } => write!(f, "{}: invalid signature", id),
+            Violation::MalformedSignature { id, error } => {
+                write!(f, "{}: malformed signature {}", id, error)
+            }
+            Violation::PayloadHashMismatch { id } => {
+                write!(f, "{}: payload_sha512 does not match payload", id)
+            }
+            Violation::BatcherMismatch {
+                id,
+                batcher_public_key,
+                batch_signer,
+            } => write!(
+                f,
+                "{}: batcher_public_key {} is not the batch signer {}",
+                id, batcher_public_key, batch_signer
+            ),
+            Violation::TransactionIdsMismatch {
+                id,
+                header,
+                transactions,
+            } => write!(
+                f,
+                "{}: transaction_ids {:?} do not match transactions {:?}",
+                id, header, transactions
+            ),
+        }
+    }
+}
+
+/// All violations found, empty if everything checks out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn push(&mut self, violation: Violation) {
+        self.violations.push(violation);
+    }
+
+    pub(crate) fn extend(&mut self, other: Report) {
+        self.violations.extend(other.violations);
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_ok() {
+            return write!(f, "ok");
+        }
+
+        for (idx, violation) in self.violations.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+struct Checker {
+    verifiers: HashMap<String, Verifier>,
+    report: Report,
+}
+
+impl Checker {
+    fn new() -> Self {
+        Checker {
+            verifiers: HashMap::new(),
+            report: Report::default(),
+        }
+    }
+
+    fn header<T: protobuf::Message>(&mut self, id: &str, bytes: &[u8]) -> Option<T> {
+        match crate::parse::<T>(bytes) {
+            Ok(header) => Some(header),
+            Err(e) => {
+                self.report.push(Violation::MalformedHeader {
+                    id: id.to_string(),
+                    error: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    fn signature(&mut self, id: &str, header: &[u8], public_key: &str) {
+        if !self.verifiers.contains_key(public_key) {
+            match Verifier::from_hex(public_key) {
+                Ok(x) => {
+                    self.verifiers.insert(public_key.to_string(), x);
+                }
+                Err(Error::InvalidPublicKey(_)) => {
+                    self.report.push(Violation::InvalidPublicKey {
+                        id: id.to_string(),
+                        public_key: public_key.to_string(),
+                    });
+                    return;
+                }
+                Err(e) => {
+                    self.report.push(Violation::MalformedSignature {
+                        id: id.to_string(),
+                        error: e.to_string(),
+                    });
+                    return;
+                }
+            }
+        }
+
+        match self.verifiers[public_key].verify(id, header) {
+            Ok(true) => {}
+            Ok(false) => self
+                .report
+                .push(Violation::InvalidSignature { id: id.to_string() }),
+            Err(e) => self.report.push(Violation::MalformedSignature {
+                id: id.to_string(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    fn transaction(&mut self, tx: &Transaction) -> Option<TransactionHeader> {
+        let id = &tx.header_signature;
+        let header = self.header::<TransactionHeader>(id, &tx.header)?;
+
+        self.signature(id, &tx.header, &header.signer_public_key);
+        if header.payload_sha512 != sha512_bytes(&tx.payload) {
+            self.report
+                .push(Violation::PayloadHashMismatch { id: id.to_string() });
+        }
+        Some(header)
+    }
+
+    fn batch(&mut self, batch: &Batch) {
+        let id = &batch.header_signature;
+        let header = match self.header::<BatchHeader>(id, &batch.header) {
+            Some(x) => x,
+            None => {
+                for tx in batch.transactions.iter() {
+                    self.transaction(tx);
+                }
+                return;
+            }
+        };
+
+        self.signature(id, &batch.header, &header.signer_public_key);
+
+        for tx in batch.transactions.iter() {
+            if let Some(tx_header) = self.transaction(tx) {
+                if tx_header.batcher_public_key != header.signer_public_key {
+                    self.report.push(Violation::BatcherMismatch {
+                        id: tx.header_signature.clone(),
+                        batcher_public_key: tx_header.batcher_public_key,
+                        batch_signer: header.signer_public_key.clone(),
+                    });
+                }
+            }
+        }
+
+        let ids: Vec<String> = batch
+            .transactions
+            .iter()
+            .map(|x| x.header_signature.clone())
+            .collect();
+        if header.transaction_ids.as_slice() != ids.as_slice() {
+            self.report.push(Violation::TransactionIdsMismatch {
+                id: id.to_string(),
+                header: header.transaction_ids.into_vec(),
+                transactions: ids,
+            });
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+
+/// Checks the signature and the payload hash of `tx`.
+pub fn verify_transaction(tx: &Transaction) -> Report {
+    let mut checker = Checker::new();
+    checker.transaction(tx);
+    checker.report
+}
+
+/// Checks the signature of `batch`, all its transactions, that they are
+/// batched by the batch signer, and that they are listed in the header in
+/// order.
+pub fn verify_batch(batch: &Batch) -> Report {
+    let mut checker = Checker::new();
+    checker.batch(batch);
+    checker.report
+}
+
+/// Like `verify_transaction`, and also checks that `tx` is declared to be
+/// batched by `batcher_public_key`.
+pub fn verify_for_batcher(tx: &Transaction, batcher_public_key: &str) -> Report {
+    let mut checker = Checker::new();
+    if let Some(header) = checker.transaction(tx) {
+        if header.batcher_public_key != batcher_public_key {
+            checker.report.push(Violation::BatcherMismatch {
+                id: tx.header_signature.clone(),
+                batcher_public_key: header.batcher_public_key,
+                batch_signer: batcher_public_key.to_string(),
+            });
+        }
+    }
+    checker.report
+}
+
+pub fn verify_batch_list(list: &BatchList) -> Report {
+    let mut checker = Checker::new();
+    for batch in list.batches.iter() {
+        checker.batch(batch);
+    }
+    checker.report
+}
+
+// ----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::Signer;
+    use crate::test_util::{builder, signer};
+    use crate::tx::Batcher;
+
+    static KEY2_PRIV_HEX: &str = "51b845c2cdde22fe646148f0b51eaf5feec8c82ee921d5e0cbe7619f3bb9c62d";
+    static KEY2_PUB_HEX: &str =
+        "039c20a66b4ec7995391dbec1d8bb0e2c6e6fd63cd259ed5b877cb4ea98858cf6d";
+
+    fn transaction(signer: &Signer, batcher: &str, nonce: &str) -> Transaction {
+        builder(signer)
+            .batcher(batcher)
+            .nonce(nonce)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_verify_transaction() {
+        let signer = signer();
+        let mut tx = transaction(&signer, KEY2_PUB_HEX, "n1");
+        assert!(verify_transaction(&tx).is_ok());
+
+        tx.payload = vec![4];
+        let report = verify_transaction(&tx);
+        assert_eq!(
+            vec![Violation::PayloadHashMismatch {
+                id: tx.header_signature.clone()
+            }],
+            report.violations
+        );
+
+        tx.header_signature = "00".repeat(64);
+        assert_eq!(2, verify_transaction(&tx).violations.len());
+
+        tx.header_signature = "zz".to_string();
+        match &verify_transaction(&tx).violations[0] {
+            Violation::MalformedSignature { id, .. } => assert_eq!("zz", id),
+            x => panic!("unexpected {}", x),
+        }
+
+        tx.header = vec![0xff];
+        match &verify_transaction(&tx).violations[0] {
+            Violation::MalformedHeader { .. } => {}
+            x => panic!("unexpected {}", x),
+        }
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let signer = signer();
+        let batch_signer = Signer::from_hex(KEY2_PRIV_HEX).unwrap();
+        let batcher = Batcher::new(&batch_signer);
+
+        let tx1 = transaction(&signer, KEY2_PUB_HEX, "n1");
+        let tx2 = transaction(&signer, KEY2_PUB_HEX, "n2");
+        let batch = batcher.build(&[tx1, tx2]).unwrap();
+        assert!(verify_batch(&batch).is_ok());
+        assert!(verify_batch_list(&Batcher::to_list(std::slice::from_ref(&batch))).is_ok());
+
+        // swapped transactions and a transaction of another batcher.
+        let mut bad = batch.clone();
+        bad.transactions.swap(0, 1);
+        let tx3 = transaction(&signer, &signer.get_public_key().unwrap(), "n3");
+        bad.transactions.push(tx3.clone());
+
+        let report = verify_batch_list(&Batcher::to_list(&[batch, bad]));
+        assert_eq!(2, report.violations.len());
+        match &report.violations[0] {
+            Violation::BatcherMismatch { id, .. } => assert_eq!(&tx3.header_signature, id),
+            x => panic!("unexpected {}", x),
+        }
+        match &report.violations[1] {
+            Violation::TransactionIdsMismatch {
+                header,
+                transactions,
+                ..
+            } => {
+                assert_eq!(2, header.len());
+                assert_eq!(3, transactions.len());
+            }
+            x => panic!("unexpected {}", x),
+        }
+    }
+}
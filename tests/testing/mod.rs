@@ -0,0 +1,4 @@
+//! Messages of `tests/protos`, generated by rust-protobuf 2 and checked in so
+//! that building the library does not compile them.
+
+pub mod validate;
@@ -0,0 +1,356 @@
+// This file is generated by rust-protobuf 2.28.0. Do not edit
+// @generated
+
+// https://github.com/rust-lang/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![allow(unused_attributes)]
+#![cfg_attr(rustfmt, rustfmt::skip)]
+
+#![allow(box_pointers)]
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unused_imports)]
+#![allow(unused_results)]
+//! Generated file from `validate.proto`
+
+/// Generated files are compatible only with the same version
+/// of protobuf runtime.
+// const _PROTOBUF_VERSION_CHECK: () = ::protobuf::VERSION_2_28_0;
+
+#[derive(PartialEq,Clone,Default)]
+pub struct Account {
+    // message fields
+    pub name: ::std::string::String,
+    pub signer: ::std::string::String,
+    pub inputs: ::protobuf::RepeatedField<::std::string::String>,
+    pub balance: i64,
+    pub rate: f64,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Account {
+    fn default() -> &'a Account {
+        <Account as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Account {
+    pub fn new() -> Account {
+        ::std::default::Default::default()
+    }
+
+    // string name = 1;
+
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    pub fn clear_name(&mut self) {
+        self.name.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_name(&mut self, v: ::std::string::String) {
+        self.name = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_name(&mut self) -> &mut ::std::string::String {
+        &mut self.name
+    }
+
+    // Take field
+    pub fn take_name(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.name, ::std::string::String::new())
+    }
+
+    // string signer = 2;
+
+
+    pub fn get_signer(&self) -> &str {
+        &self.signer
+    }
+    pub fn clear_signer(&mut self) {
+        self.signer.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_signer(&mut self, v: ::std::string::String) {
+        self.signer = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_signer(&mut self) -> &mut ::std::string::String {
+        &mut self.signer
+    }
+
+    // Take field
+    pub fn take_signer(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.signer, ::std::string::String::new())
+    }
+
+    // repeated string inputs = 3;
+
+
+    pub fn get_inputs(&self) -> &[::std::string::String] {
+        &self.inputs
+    }
+    pub fn clear_inputs(&mut self) {
+        self.inputs.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_inputs(&mut self, v: ::protobuf::RepeatedField<::std::string::String>) {
+        self.inputs = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_inputs(&mut self) -> &mut ::protobuf::RepeatedField<::std::string::String> {
+        &mut self.inputs
+    }
+
+    // Take field
+    pub fn take_inputs(&mut self) -> ::protobuf::RepeatedField<::std::string::String> {
+        ::std::mem::replace(&mut self.inputs, ::protobuf::RepeatedField::new())
+    }
+
+    // sint64 balance = 4;
+
+
+    pub fn get_balance(&self) -> i64 {
+        self.balance
+    }
+    pub fn clear_balance(&mut self) {
+        self.balance = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_balance(&mut self, v: i64) {
+        self.balance = v;
+    }
+
+    // double rate = 5;
+
+
+    pub fn get_rate(&self) -> f64 {
+        self.rate
+    }
+    pub fn clear_rate(&mut self) {
+        self.rate = 0.;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_rate(&mut self, v: f64) {
+        self.rate = v;
+    }
+}
+
+impl ::protobuf::Message for Account {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.name)?;
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.signer)?;
+                },
+                3 => {
+                    ::protobuf::rt::read_repeated_string_into(wire_type, is, &mut self.inputs)?;
+                },
+                4 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_sint64()?;
+                    self.balance = tmp;
+                },
+                5 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeFixed64 {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_double()?;
+                    self.rate = tmp;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if !self.name.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.name);
+        }
+        if !self.signer.is_empty() {
+            my_size += ::protobuf::rt::string_size(2, &self.signer);
+        }
+        for value in &self.inputs {
+            my_size += ::protobuf::rt::string_size(3, &value);
+        };
+        if self.balance != 0 {
+            my_size += ::protobuf::rt::value_varint_zigzag_size(4, self.balance);
+        }
+        if self.rate != 0. {
+            my_size += 9;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if !self.name.is_empty() {
+            os.write_string(1, &self.name)?;
+        }
+        if !self.signer.is_empty() {
+            os.write_string(2, &self.signer)?;
+        }
+        for v in &self.inputs {
+            os.write_string(3, &v)?;
+        };
+        if self.balance != 0 {
+            os.write_sint64(4, self.balance)?;
+        }
+        if self.rate != 0. {
+            os.write_double(5, self.rate)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: ::std::boxed::Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Account {
+        Account::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::LazyV2<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::LazyV2::INIT;
+        descriptor.get(|| {
+            let mut fields = ::std::vec::Vec::new();
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                "name",
+                |m: &Account| { &m.name },
+                |m: &mut Account| { &mut m.name },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                "signer",
+                |m: &Account| { &m.signer },
+                |m: &mut Account| { &mut m.signer },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_repeated_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                "inputs",
+                |m: &Account| { &m.inputs },
+                |m: &mut Account| { &mut m.inputs },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeSint64>(
+                "balance",
+                |m: &Account| { &m.balance },
+                |m: &mut Account| { &mut m.balance },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeDouble>(
+                "rate",
+                |m: &Account| { &m.rate },
+                |m: &mut Account| { &mut m.rate },
+            ));
+            ::protobuf::reflect::MessageDescriptor::new_pb_name::<Account>(
+                "Account",
+                fields,
+                file_descriptor_proto()
+            )
+        })
+    }
+
+    fn default_instance() -> &'static Account {
+        static instance: ::protobuf::rt::LazyV2<Account> = ::protobuf::rt::LazyV2::INIT;
+        instance.get(Account::new)
+    }
+}
+
+impl ::protobuf::Clear for Account {
+    fn clear(&mut self) {
+        self.name.clear();
+        self.signer.clear();
+        self.inputs.clear();
+        self.balance = 0;
+        self.rate = 0.;
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Account {
+    fn as_ref(&self) -> ::protobuf::reflect::ReflectValueRef {
+        ::protobuf::reflect::ReflectValueRef::Message(self)
+    }
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\x0evalidate.proto\x12\x07testing\"{\n\x07Account\x12\x12\n\x04name\
+    \x18\x01\x20\x01(\tR\x04name\x12\x16\n\x06signer\x18\x02\x20\x01(\tR\x06\
+    signer\x12\x16\n\x06inputs\x18\x03\x20\x03(\tR\x06inputs\x12\x18\n\x07ba\
+    lance\x18\x04\x20\x01(\x12R\x07balance\x12\x12\n\x04rate\x18\x05\x20\x01\
+    (\x01R\x04rateb\x06proto3\
+";
+
+static file_descriptor_proto_lazy: ::protobuf::rt::LazyV2<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::rt::LazyV2::INIT;
+
+fn parse_descriptor_proto() -> ::protobuf::descriptor::FileDescriptorProto {
+    ::protobuf::Message::parse_from_bytes(file_descriptor_proto_data).unwrap()
+}
+
+pub fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    file_descriptor_proto_lazy.get(|| {
+        parse_descriptor_proto()
+    })
+}
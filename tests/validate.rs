@@ -0,0 +1,102 @@
+/*
+ * MIT License
+ *
+ * Copyright (c) 2019 Kigi Chang
+
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+*/
+
+//! Compiles `validate!` over a generated message and runs the checks.
+
+#![cfg(feature = "derive")]
+
+mod testing;
+
+use sawtk::namespace;
+use sawtk::tp::{self, Validate};
+use testing::validate::Account;
+
+sawtk::tp::validate!("tests/validate.toml");
+
+static KEY_PUB_HEX: &str = "026a2c795a9776f75464aa3bda3534c3154a6e91b357b1181d3f515110f84b67c5";
+
+fn account() -> Account {
+    Account {
+        name: "alice".to_string(),
+        signer: KEY_PUB_HEX.to_string(),
+        inputs: vec![namespace::new("intkey").make_address("a1")].into(),
+        balance: -100,
+        rate: 1.5,
+        ..Account::default()
+    }
+}
+
+fn rejected(mut msg: Account, reason: &str) {
+    match msg.validate() {
+        Err(tp::Error::Invalid(x)) => assert_eq!(reason, x),
+        x => panic!("{} not rejected: {:?}", reason, x),
+    }
+}
+
+#[test]
+fn test_validate_accepts() {
+    account().validate().unwrap();
+
+    let mut msg = account();
+    msg.balance = 100;
+    msg.rate = -0.5;
+    msg.inputs.clear();
+    msg.validate().unwrap();
+}
+
+#[test]
+fn test_validate_rejects() {
+    let mut msg = account();
+    msg.name.clear();
+    rejected(msg, "Account.name: empty");
+
+    let mut msg = account();
+    msg.signer = KEY_PUB_HEX[2..].to_string();
+    rejected(msg, "Account.signer: invalid public key");
+
+    let mut msg = account();
+    msg.inputs.push(String::new());
+    rejected(msg, "Account.inputs: empty");
+
+    let mut msg = account();
+    msg.inputs.push("1cf126".to_string());
+    rejected(msg, "Account.inputs: invalid address");
+
+    let mut msg = account();
+    msg.balance = -101;
+    rejected(msg, "Account.balance: less than -100");
+
+    let mut msg = account();
+    msg.balance = 101;
+    rejected(msg, "Account.balance: greater than 100");
+
+    let mut msg = account();
+    msg.rate = -0.51;
+    rejected(msg, "Account.rate: less than -0.5");
+
+    let mut msg = account();
+    msg.rate = 1.51;
+    rejected(msg, "Account.rate: greater than 1.5");
+}
@@ -0,0 +1,281 @@
+#[macro_use]
+extern crate clap;
+extern crate sawtk;
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use protobuf::Message;
+use sawtk::client::rest::RestClient;
+use sawtk::messages::template::{TransactionTemplate, TransactionTemplates};
+use sawtk::signing;
+use sawtk::tx;
+use sawtk::util;
+use sawtooth_sdk::messages::batch::BatchList;
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+fn read_message<T: Message>(file_name: &str) -> Result<T, String> {
+    let bytes = fs::read(file_name).map_err(|e| format!("couldn't read {}: {}", file_name, e))?;
+    let mut msg = T::new();
+    msg.merge_from_bytes(&bytes)
+        .map_err(|e| format!("couldn't decode {}: {}", file_name, e))?;
+    Ok(msg)
+}
+
+fn write_message(file_name: &str, msg: &dyn Message) -> Result<(), String> {
+    let bytes = msg
+        .write_to_bytes()
+        .map_err(|e| format!("couldn't encode {}: {}", file_name, e))?;
+    fs::write(file_name, bytes).map_err(|e| format!("couldn't write to {}: {}", file_name, e))
+}
+
+fn values(m: &ArgMatches, name: &str) -> Vec<String> {
+    m.values_of(name)
+        .map(|x| x.map(|v| v.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn prepare(m: &ArgMatches) -> Result<(), String> {
+    let file_name = m.value_of("file").unwrap();
+    let mut templates = if Path::new(file_name).exists() {
+        read_message::<TransactionTemplates>(file_name)?
+    } else {
+        TransactionTemplates::default()
+    };
+
+    let payload = match (m.value_of("payload"), m.value_of("payload-file")) {
+        (Some(hex), _) => util::hex_str_to_bytes(hex).map_err(|e| e.to_string())?,
+        (None, Some(name)) => {
+            fs::read(name).map_err(|e| format!("couldn't read {}: {}", name, e))?
+        }
+        (None, None) => return Err("payload or payload-file required".to_string()),
+    };
+
+    let mut after = Vec::new();
+    for x in values(m, "after") {
+        after.push(
+            x.parse::<u32>()
+                .map_err(|e| format!("after {}: {}", x, e))?,
+        );
+    }
+
+    let idx = templates.push(TransactionTemplate {
+        family_name: m.value_of("family").unwrap().to_string(),
+        family_version: m.value_of("version").unwrap().to_string(),
+        inputs: values(m, "input").into(),
+        outputs: values(m, "output").into(),
+        payload,
+        dependencies: values(m, "dependency").into(),
+        nonce: m.value_of("nonce").unwrap_or_default().to_string(),
+        after,
+        ..TransactionTemplate::default()
+    });
+
+    write_message(file_name, &templates)?;
+    println!("transaction #{} added to {}", idx, file_name);
+    Ok(())
+}
+
+fn sign(m: &ArgMatches) -> Result<(), String> {
+    let key_file = m.value_of("key").unwrap();
+    let bytes = fs::read(key_file).map_err(|e| format!("couldn't read {}: {}", key_file, e))?;
+    let signer =
+        signing::Signer::from_hex(&util::bytes_to_hex_str(&bytes)).map_err(|e| e.to_string())?;
+
+    let templates = read_message::<TransactionTemplates>(m.value_of("input").unwrap())?;
+    let list = tx::sign_templates(&signer, &templates, &tx::Limits::default())
+        .map_err(|e| e.to_string())?;
+
+    let output = m.value_of("output").unwrap();
+    write_message(output, &list)?;
+    for batch in list.batches.iter() {
+        println!("batch: {}", batch.header_signature);
+        for x in batch.transactions.iter() {
+            println!("  transaction: {}", x.header_signature);
+        }
+    }
+    Ok(())
+}
+
+fn show(m: &ArgMatches) -> Result<(), String> {
+    let list = read_message::<BatchList>(m.value_of("input").unwrap())?;
+    let node = if m.is_present("request") {
+        tx::inspect_batch_list_with_request(&list)
+    } else {
+        tx::inspect_batch_list(&list)
+    };
+    if m.is_present("json") {
+        println!("{}", node.to_json());
+    } else {
+        print!("{}", node.to_text());
+    }
+    Ok(())
+}
+
+fn submit(m: &ArgMatches) -> Result<(), String> {
+    let list = read_message::<BatchList>(m.value_of("input").unwrap())?;
+
+    let report = tx::verify_batch_list(&list);
+    if !report.is_ok() {
+        return Err(report.to_string());
+    }
+
+    let client = RestClient::new(m.value_of("url").unwrap());
+    let wait = value_t!(m, "wait", u64).map_err(|e| e.to_string())?;
+    if wait == 0 {
+        for id in client.submit(&list).map_err(|e| e.to_string())? {
+            println!("batch: {}", id);
+        }
+    } else {
+        let statuses = client
+            .submit_and_wait(&list, Duration::from_secs(wait))
+            .map_err(|e| e.to_string())?;
+        for x in statuses.iter() {
+            println!("batch: {} {:?}", x.id, x.status);
+            for invalid in x.invalid_transactions.iter() {
+                println!("  invalid: {} {}", invalid.id, invalid.message);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), String> {
+    let matches = App::new("offline batch tool")
+        .version(crate_version!())
+        .author("Kigi Chang <kigi.chang@gmail.com>")
+        .subcommand(
+            SubCommand::with_name("prepare")
+                .about("add an unsigned transaction to a template file")
+                .args(&[
+                    Arg::with_name("file")
+                        .long("file")
+                        .short("f")
+                        .required(true)
+                        .help("template file, created if not exists")
+                        .takes_value(true),
+                    Arg::with_name("family")
+                        .long("family")
+                        .required(true)
+                        .help("family name")
+                        .takes_value(true),
+                    Arg::with_name("version")
+                        .long("version")
+                        .required(true)
+                        .help("family version")
+                        .takes_value(true),
+                    Arg::with_name("payload")
+                        .long("payload")
+                        .help("payload in hex")
+                        .takes_value(true),
+                    Arg::with_name("payload-file")
+                        .long("payload-file")
+                        .help("file of payload bytes")
+                        .takes_value(true),
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .help("input address or prefix")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .help("output address or prefix")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                    Arg::with_name("dependency")
+                        .long("dependency")
+                        .help("id of a committed transaction")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                    Arg::with_name("after")
+                        .long("after")
+                        .help("index of an earlier transaction in the file")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .takes_value(true),
+                    Arg::with_name("nonce")
+                        .long("nonce")
+                        .help("nonce, generated when signing if not set")
+                        .takes_value(true),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("sign")
+                .about("sign a template file into a batch list file")
+                .args(&[
+                    Arg::with_name("key")
+                        .long("key")
+                        .short("k")
+                        .required(true)
+                        .help("private key file")
+                        .takes_value(true),
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .required(true)
+                        .help("template file")
+                        .takes_value(true),
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .required(true)
+                        .help("batch list file")
+                        .takes_value(true),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("show a batch list file")
+                .args(&[
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .required(true)
+                        .help("batch list file")
+                        .takes_value(true),
+                    Arg::with_name("json").long("json").help("print as json"),
+                    Arg::with_name("request")
+                        .long("request")
+                        .help("decode payloads as TPRequest"),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("submit")
+                .about("submit a batch list file to the rest api")
+                .args(&[
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .required(true)
+                        .help("batch list file")
+                        .takes_value(true),
+                    Arg::with_name("url")
+                        .long("url")
+                        .short("u")
+                        .default_value("http://localhost:8008")
+                        .help("url of the rest api")
+                        .takes_value(true),
+                    Arg::with_name("wait")
+                        .long("wait")
+                        .short("w")
+                        .default_value("0")
+                        .help("seconds to wait for commit")
+                        .takes_value(true),
+                ]),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("prepare", Some(m)) => prepare(m),
+        ("sign", Some(m)) => sign(m),
+        ("show", Some(m)) => show(m),
+        ("submit", Some(m)) => submit(m),
+        _ => Err(matches.usage().to_string()),
+    }
+}
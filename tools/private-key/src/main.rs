@@ -8,7 +8,7 @@ use sawtk::util;
 use sawtk::wallet;
 
 use std::error::Error;
-use std::fs::{self, File};
+use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
 use std::path::Path;
 
@@ -25,6 +25,25 @@ fn write_file(file_name: &str, data: &[u8]) -> Result<(), String> {
     Ok(())
 }
 
+/// Writes a secret readable by the owner only, refusing to overwrite a file.
+fn write_secret_file(file_name: &str, data: &[u8]) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(file_name)
+        .map_err(|e| format!("couldn't create {}: {}", file_name, e))?;
+
+    file.write_all(data)
+        .map_err(|e| format!("couldn't write to {}: {}", file_name, e))
+}
+
 fn gen_random_private_key(name: &str) {
     let ctx = signing::create_context().expect("init context failure");
 
@@ -63,6 +82,52 @@ fn load_private_key(file_name: &str) {
     );
 }
 
+fn read_password(confirm: bool) -> Result<String, String> {
+    let password = rpassword::prompt_password("password: ")
+        .map_err(|e| format!("couldn't read password: {}", e))?;
+
+    if confirm {
+        let again = rpassword::prompt_password("confirm password: ")
+            .map_err(|e| format!("couldn't read password: {}", e))?;
+        if again != password {
+            return Err("passwords do not match".to_string());
+        }
+    }
+
+    Ok(password)
+}
+
+fn encrypt_private_key(input: &str, output: &str) -> Result<(), String> {
+    let bytes = fs::read(input).map_err(|e| format!("couldn't read {}: {}", input, e))?;
+    let signer =
+        signing::Signer::from_hex(&util::bytes_to_hex_str(&bytes)).map_err(|e| e.to_string())?;
+
+    let password = read_password(true)?;
+    let json = signer.to_keystore(&password).map_err(|e| e.to_string())?;
+    write_secret_file(output, json.as_bytes())?;
+
+    println!(
+        "public key: {}",
+        signer.get_public_key().map_err(|e| e.to_string())?
+    );
+    println!("keystore: {}", output);
+    Ok(())
+}
+
+fn decrypt_private_key(input: &str, output: &str) -> Result<(), String> {
+    let json = fs::read_to_string(input).map_err(|e| format!("couldn't read {}: {}", input, e))?;
+    let keystore = signing::Keystore::from_json(&json).map_err(|e| e.to_string())?;
+    println!("public key: {}", keystore.public_key);
+    println!("wallet: {}", keystore.wallet);
+
+    let password = read_password(false)?;
+    let signer = keystore.decrypt(&password).map_err(|e| e.to_string())?;
+    write_secret_file(output, &signer.private_key_bytes())?;
+
+    println!("private key: {}", output);
+    Ok(())
+}
+
 fn main() -> Result<(), String> {
     let matches = App::new("private key tool")
         .version(crate_version!())
@@ -91,6 +156,42 @@ fn main() -> Result<(), String> {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("encrypt")
+                .about("encrypt a private key file into a keystore")
+                .args(&[
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .required(true)
+                        .help("private key file to encrypt")
+                        .takes_value(true),
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .required(true)
+                        .help("keystore file to write")
+                        .takes_value(true),
+                ]),
+        )
+        .subcommand(
+            SubCommand::with_name("decrypt")
+                .about("decrypt a keystore into a private key file")
+                .args(&[
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .required(true)
+                        .help("keystore file to decrypt")
+                        .takes_value(true),
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .required(true)
+                        .help("private key file to write")
+                        .takes_value(true),
+                ]),
+        )
         .get_matches();
 
     if let Some(m) = matches.subcommand_matches("gen") {
@@ -100,6 +201,10 @@ fn main() -> Result<(), String> {
     } else if let Some(m) = matches.subcommand_matches("load") {
         let file_name = m.value_of("input").unwrap();
         load_private_key(&file_name);
+    } else if let Some(m) = matches.subcommand_matches("encrypt") {
+        encrypt_private_key(m.value_of("input").unwrap(), m.value_of("output").unwrap())?;
+    } else if let Some(m) = matches.subcommand_matches("decrypt") {
+        decrypt_private_key(m.value_of("input").unwrap(), m.value_of("output").unwrap())?;
     }
 
     Ok(())